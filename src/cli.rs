@@ -0,0 +1,357 @@
+// src/cli.rs
+use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use rand::Rng;
+
+use crate::compare::CompareArgs;
+use crate::generate_data::GenerationSettings;
+use crate::postgres::{PgIndexProfile, PgInsertStrategy};
+use crate::report::Backend;
+
+const DEFAULT_TABLE_NAME: &str = "documents_jsonb";
+const DEFAULT_INDEX_NAME: &str = "documents_jsonb";
+// Fixed so that created_at values do not drift with the wall clock between runs
+const DEFAULT_REFERENCE_TIME: &str = "2025-01-01T00:00:00Z";
+
+#[derive(Parser, Debug)]
+#[command(version, about = "PostgreSQL JSONB vs Elasticsearch benchmark")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    // Running without a subcommand behaves like `all`
+    #[command(flatten)]
+    pub all: AllArgs,
+
+    #[command(flatten)]
+    pub log: LogArgs,
+}
+
+impl Cli {
+    // Parses the command line. Options of the implicit `all` run conflict with subcommands, which
+    // `args_conflicts_with_subcommands` would also apply to the global logging options.
+    pub fn parse_args() -> Self {
+        let mut command = Cli::command();
+        let matches = command.get_matches_mut();
+        if let Some((name, _)) = matches.subcommand() {
+            let conflicting = command.get_arguments().find(|arg| {
+                !arg.is_global_set() && matches.try_contains_id(arg.get_id().as_str()).unwrap_or(false)
+                    && matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            });
+            if let Some(arg) = conflicting {
+                let flag = arg.get_long().map_or_else(|| arg.get_id().to_string(), |long| format!("--{long}"));
+                command.error(ErrorKind::ArgumentConflict, format!("the subcommand '{name}' cannot be used with '{flag}'")).exit();
+            }
+        }
+        Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per event
+    Json,
+}
+
+// Logging options, accepted before or after any subcommand
+#[derive(Args, Debug, Clone)]
+pub struct LogArgs {
+    /// Log level (error, warn, info, debug, trace); RUST_LOG takes precedence
+    #[arg(long, global = true, default_value = "info", env = "LOG_LEVEL")]
+    pub log_level: String,
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text, env = "LOG_FORMAT")]
+    pub log_format: LogFormat,
+    /// Only log warnings and errors and hide progress bars
+    #[arg(short, long, global = true, env = "QUIET")]
+    pub quiet: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Create the PostgreSQL table and Elasticsearch index
+    Setup(SetupArgs),
+    /// Generate documents into an NDJSON file
+    Generate(GenerateArgs),
+    /// Insert generated (or NDJSON) documents into the selected backends
+    Load(LoadArgs),
+    /// Run the query workload against already-loaded data
+    Bench(BenchCommandArgs),
+    /// Drop the PostgreSQL table and Elasticsearch index
+    Drop(TargetArgs),
+    /// Setup, load and bench in one go (default)
+    #[command(alias = "run")]
+    All(AllArgs),
+    /// Compare two saved runs and fail on significant regressions
+    Compare(CompareArgs),
+    /// Check that both backends match the same documents for every paired query
+    Verify(VerifyArgs),
+}
+
+// Which backends to talk to and which table/index to use on them
+#[derive(Args, Debug, Clone)]
+pub struct TargetArgs {
+    /// Backends to run against
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [Backend::Postgres, Backend::Elasticsearch], env = "BACKENDS")]
+    pub backends: Vec<Backend>,
+    /// PostgreSQL table holding the JSONB documents
+    #[arg(long, default_value = DEFAULT_TABLE_NAME, env = "PG_TABLE_NAME")]
+    pub table: String,
+    /// Elasticsearch index holding the documents
+    #[arg(long, default_value = DEFAULT_INDEX_NAME, env = "ES_INDEX_NAME")]
+    pub index: String,
+    /// Secondary indexes to create on the PostgreSQL table
+    #[arg(long, value_enum, default_value_t = PgIndexProfile::JsonbOps, env = "PG_INDEX_PROFILE")]
+    pub pg_index_profile: PgIndexProfile,
+}
+
+impl TargetArgs {
+    pub fn postgres(&self) -> bool {
+        self.backends.contains(&Backend::Postgres)
+    }
+
+    pub fn elasticsearch(&self) -> bool {
+        self.backends.contains(&Backend::Elasticsearch)
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExistingMode {
+    /// Keep an existing table/index and its data; new documents are appended
+    Reuse,
+    /// Keep an existing table/index but delete its documents
+    Truncate,
+    /// Drop an existing table/index and create it from scratch
+    Recreate,
+}
+
+// What setup does with a table or index left over from an earlier run
+#[derive(Args, Debug, Clone)]
+pub struct SchemaArgs {
+    /// Existing Elasticsearch indexes must match the expected mapping unless recreated
+    #[arg(long, value_enum, default_value_t = ExistingMode::Reuse, env = "EXISTING_MODE")]
+    pub existing: ExistingMode,
+}
+
+// Inputs of the document generator
+#[derive(Args, Debug, Clone)]
+pub struct GenerationArgs {
+    /// Number of documents to generate
+    #[arg(long, default_value_t = 1_000, env = "DATA_COUNT")]
+    pub count: usize,
+    /// RNG seed; the same seed yields byte-identical documents (random when not set)
+    #[arg(long, env = "DATA_SEED")]
+    pub seed: Option<u64>,
+    /// Upper end of the created_at window, as RFC 3339
+    #[arg(long, default_value = DEFAULT_REFERENCE_TIME, env = "DATA_REFERENCE_TIME")]
+    pub reference_time: DateTime<Utc>,
+}
+
+impl GenerationArgs {
+    // Resolves the seed; call once per run so every consumer sees the same corpus
+    pub fn settings(&self) -> GenerationSettings {
+        GenerationSettings {
+            count: self.count,
+            seed: self.seed.unwrap_or_else(|| rand::rng().random()),
+            reference_time: self.reference_time,
+        }
+    }
+}
+
+// Writing a few generated documents out for inspection, outside of any timed phase
+#[derive(Args, Debug, Clone)]
+pub struct SampleArgs {
+    /// Write the first N generated documents to --sample-file
+    #[arg(long, value_name = "N", env = "SAMPLE_DUMP")]
+    pub sample_dump: Option<usize>,
+    #[arg(long, default_value = "sample-documents.json", env = "SAMPLE_FILE")]
+    pub sample_file: PathBuf,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct DataArgs {
+    #[command(flatten)]
+    pub generation: GenerationArgs,
+    #[command(flatten)]
+    pub sample: SampleArgs,
+    /// Documents per Elasticsearch bulk request, and per statement/chunk for batched PostgreSQL strategies
+    #[arg(long, default_value_t = 1_000, env = "BATCH_SIZE")]
+    pub batch_size: usize,
+    /// Keep Elasticsearch bulk requests at or below this many bytes (a single larger document is sent alone)
+    #[arg(long, env = "BATCH_BYTES")]
+    pub batch_bytes: Option<usize>,
+    /// Elasticsearch bulk requests in flight at once
+    #[arg(long, default_value_t = 1, env = "ES_BULK_CONCURRENCY", value_parser = clap::value_parser!(u64).range(1..))]
+    pub es_concurrency: u64,
+    /// Load Elasticsearch with refreshes, replicas and translog fsyncs switched off, then restore the
+    /// index settings and force-merge
+    #[arg(long, env = "ES_INGEST_SETTINGS")]
+    pub es_ingest_settings: bool,
+    /// Times a bulk item rejected with 429/503 is sent again before it counts as failed
+    #[arg(long, default_value_t = 5, env = "ES_MAX_RETRIES")]
+    pub es_max_retries: u32,
+    /// Delay before the first retry; doubles with every further attempt
+    #[arg(long, default_value_t = 100, env = "ES_RETRY_BACKOFF_MS")]
+    pub es_retry_backoff_ms: u64,
+    /// Abort the load once more documents than this have been rejected
+    #[arg(long, default_value_t = 0, env = "ES_MAX_FAILED_DOCS")]
+    pub es_max_failed_docs: usize,
+    /// NDJSON file receiving rejected documents with their error, written only when something fails
+    #[arg(long, default_value = "dead-letter.ndjson", env = "DEAD_LETTER_FILE")]
+    pub dead_letter: PathBuf,
+    /// PostgreSQL insert strategies to run; with several, the table is truncated between them
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [PgInsertStrategy::CopyBinary], env = "PG_INSERT_STRATEGY")]
+    pub pg_insert_strategy: Vec<PgInsertStrategy>,
+    /// Load documents from an NDJSON file instead of generating them
+    #[arg(long)]
+    pub input: Option<PathBuf>,
+    /// Whether PostgreSQL indexes exist during the load or are built afterwards
+    #[arg(long, value_enum, default_value_t = PgIndexTiming::Before, env = "PG_INDEX_TIMING")]
+    pub pg_index_timing: PgIndexTiming,
+    /// PostgreSQL connections loading in parallel, each with its own COPY
+    #[arg(long, default_value_t = 1, env = "PG_SHARDS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub pg_shards: u64,
+    /// Documents buffered between the producer and each loader
+    #[arg(long, default_value_t = 10_000, env = "STREAM_BUFFER")]
+    pub stream_buffer: usize,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgIndexTiming {
+    /// Keep the indexes from setup, so every inserted row updates them
+    Before,
+    /// Drop the indexes before loading and build them once the data is in
+    After,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchMode {
+    /// One query at a time, each repeated after a warmup
+    Sequential,
+    /// Weighted query mix from several workers at once
+    Concurrent,
+    /// Updates and deletes by id and by query, one at a time; deletes remove documents for good
+    Writes,
+    /// Concurrent query mix while background writers keep updating documents
+    Mixed,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BenchArgs {
+    /// Query suite (.toml, .yaml or .json); defaults to the built-in workloads/default.toml
+    #[arg(long, env = "WORKLOAD_FILE")]
+    pub workload: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = BenchMode::Sequential, env = "BENCH_MODE")]
+    pub mode: BenchMode,
+    /// Measured runs per query (sequential mode)
+    #[arg(long, default_value_t = 100, env = "BENCH_ITERATIONS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub iterations: u64,
+    /// Unmeasured runs per query before measuring (sequential mode)
+    #[arg(long, default_value_t = 10, env = "BENCH_WARMUP")]
+    pub warmup: u64,
+    /// Concurrent workers per backend (concurrent mode)
+    #[arg(long, default_value_t = 8, env = "LOAD_WORKERS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub workers: u64,
+    /// Background writers per backend (mixed mode)
+    #[arg(long, default_value_t = 1, env = "LOAD_WRITERS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub writers: u64,
+    /// Run time in seconds (concurrent mode, unless --requests is set)
+    #[arg(long, default_value_t = 30, env = "LOAD_DURATION_SECS")]
+    pub duration_secs: u64,
+    /// Total number of requests per backend instead of a fixed duration (concurrent mode)
+    #[arg(long, env = "LOAD_REQUESTS")]
+    pub requests: Option<usize>,
+    /// Also benchmark an exact-count variant of every query (COUNT(*) on PostgreSQL, track_total_hits on Elasticsearch)
+    #[arg(long, env = "BENCH_COUNT_TOTALS")]
+    pub count_totals: bool,
+    /// Also run EXPLAIN (ANALYZE, BUFFERS) once per PostgreSQL query and store the plans
+    #[arg(long, env = "BENCH_EXPLAIN")]
+    pub explain: bool,
+    /// Run the PostgreSQL suite once per index profile, rebuilding indexes in between (all profiles if none are listed)
+    #[arg(long, value_enum, value_delimiter = ',', num_args = 0.., env = "PG_INDEX_MATRIX")]
+    pub pg_index_matrix: Option<Vec<PgIndexProfile>>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ReportArgs {
+    /// Write the structured results to this JSON file
+    #[arg(long, env = "REPORT_JSON")]
+    pub report_json: Option<PathBuf>,
+    /// Write the structured results to this CSV file
+    #[arg(long, env = "REPORT_CSV")]
+    pub report_csv: Option<PathBuf>,
+    /// Keep every run as a timestamped JSON file in this directory (for `compare`)
+    #[arg(long, env = "RESULTS_DIR")]
+    pub results_dir: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct GenerateArgs {
+    #[command(flatten)]
+    pub generation: GenerationArgs,
+    #[command(flatten)]
+    pub sample: SampleArgs,
+    /// NDJSON file to write, one document per line
+    #[arg(long, default_value = "documents.ndjson")]
+    pub output: PathBuf,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SetupArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+    #[command(flatten)]
+    pub schema: SchemaArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct LoadArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+    #[command(flatten)]
+    pub data: DataArgs,
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BenchCommandArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+    #[command(flatten)]
+    pub bench: BenchArgs,
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct VerifyArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+    /// Query suite (.toml, .yaml or .json); defaults to the built-in workloads/default.toml
+    #[arg(long, env = "WORKLOAD_FILE")]
+    pub workload: Option<PathBuf>,
+    /// Parameter draws to verify per query
+    #[arg(long, default_value_t = 1, env = "VERIFY_DRAWS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub draws: u64,
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct AllArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+    #[command(flatten)]
+    pub schema: SchemaArgs,
+    #[command(flatten)]
+    pub data: DataArgs,
+    #[command(flatten)]
+    pub bench: BenchArgs,
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
@@ -0,0 +1,245 @@
+// src/compare.rs
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use clap::Args;
+use tracing::warn;
+
+use crate::BenchmarkError;
+use crate::report::{Backend, BenchmarkReport, QueryReport};
+
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    /// Saved run (JSON report) to compare against
+    pub baseline: PathBuf,
+    /// Saved run (JSON report) to check for regressions
+    pub candidate: PathBuf,
+    /// Relative slowdown (in percent) of mean latency or throughput that counts as a regression
+    #[arg(long, default_value_t = 10.0)]
+    pub threshold: f64,
+    /// Critical value of Welch's t statistic for a latency change to be significant (1.96 ≈ 95%)
+    #[arg(long, default_value_t = 1.96)]
+    pub significance: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    Ok,
+    Improved,
+    Regression,
+}
+
+// Queries are matched across runs by backend, name, mode and index profile
+type QueryKey<'a> = (Backend, &'a str, &'a str, Option<&'a str>);
+
+fn query_key(query: &QueryReport) -> QueryKey<'_> {
+    (query.backend, query.name.as_str(), query.mode.as_str(), query.index_profile.as_deref())
+}
+
+fn load_report(path: &Path) -> Result<BenchmarkReport, BenchmarkError> {
+    let file = std::fs::File::open(path)?;
+    Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+}
+
+fn percent_change(baseline: f64, candidate: f64) -> f64 {
+    if baseline == 0.0 {
+        if candidate == 0.0 { 0.0 } else { f64::INFINITY }
+    } else {
+        (candidate - baseline) / baseline * 100.0
+    }
+}
+
+// Welch's t statistic for the difference in mean latency (positive = candidate slower)
+fn welch_t(baseline: &QueryReport, candidate: &QueryReport) -> f64 {
+    let (b, c) = (&baseline.latency, &candidate.latency);
+    let diff = c.mean_ms - b.mean_ms;
+    let variance = b.stddev_ms.powi(2) / b.samples.max(1) as f64
+        + c.stddev_ms.powi(2) / c.samples.max(1) as f64;
+    if variance == 0.0 {
+        if diff == 0.0 { 0.0 } else { diff.signum() * f64::INFINITY }
+    } else {
+        diff / variance.sqrt()
+    }
+}
+
+fn judge(baseline: &QueryReport, candidate: &QueryReport, args: &CompareArgs) -> (Verdict, f64) {
+    let t = welch_t(baseline, candidate);
+    let significant = t.abs() >= args.significance;
+    let latency_delta = percent_change(baseline.latency.mean_ms, candidate.latency.mean_ms);
+    let throughput_delta = percent_change(baseline.throughput_qps, candidate.throughput_qps);
+
+    let verdict = if significant && t > 0.0 && (latency_delta > args.threshold || throughput_delta < -args.threshold) {
+        Verdict::Regression
+    } else if significant && t < 0.0 && latency_delta < -args.threshold {
+        Verdict::Improved
+    } else {
+        Verdict::Ok
+    };
+    (verdict, t)
+}
+
+// Prints per-query deltas; returns the number of regressions found
+pub fn compare_runs(args: &CompareArgs) -> Result<usize, BenchmarkError> {
+    let baseline = load_report(&args.baseline)?;
+    let candidate = load_report(&args.candidate)?;
+
+    println!("Baseline:  {} ({} docs, started {})", args.baseline.display(), baseline.data_count, baseline.started_at);
+    println!("Candidate: {} ({} docs, started {})", args.candidate.display(), candidate.data_count, candidate.started_at);
+    if baseline.data_count != candidate.data_count {
+        warn!("Runs used different data counts; deltas may not be comparable.");
+    }
+    if let (Some(base_seed), Some(cand_seed)) = (baseline.seed, candidate.seed) {
+        if base_seed != cand_seed {
+            warn!("Runs used different data seeds ({} vs {}); corpora differ.", base_seed, cand_seed);
+        }
+    }
+    println!(
+        "Regression threshold: {:.1}% (significance |t| >= {:.2})\n",
+        args.threshold, args.significance
+    );
+
+    let baseline_queries: HashMap<QueryKey, &QueryReport> = baseline.queries.iter()
+        .map(|q| (query_key(q), q))
+        .collect();
+
+    println!(
+        "{:<14} | {:<30} | {:<26} | {:>10} | {:>10} | {:>8} | {:>10} | {:>10} | {:>8} | {:>10} | {:>8} | {:>8} | {:<10}",
+        "Backend", "Query", "Mode", "mean(base)", "mean(cand)", "Δmean%", "p99(base)", "p99(cand)", "Δp99%", "QPS(cand)", "ΔQPS%", "t", "Verdict"
+    );
+    println!("{:-<196}", "");
+
+    let mut regressions = 0;
+    for cand in &candidate.queries {
+        let Some(base) = baseline_queries.get(&query_key(cand)) else {
+            println!("{:<14} | {:<30} | {:<26} | (not present in baseline)", cand.backend, cand.name, cand.mode_label());
+            continue;
+        };
+
+        let (verdict, t) = judge(base, cand, args);
+        if verdict == Verdict::Regression {
+            regressions += 1;
+        }
+        println!(
+            "{:<14} | {:<30} | {:<26} | {:>10.4} | {:>10.4} | {:>+8.2} | {:>10.4} | {:>10.4} | {:>+8.2} | {:>10.2} | {:>+8.2} | {:>8.2} | {:<10}",
+            cand.backend,
+            cand.name,
+            cand.mode_label(),
+            base.latency.mean_ms,
+            cand.latency.mean_ms,
+            percent_change(base.latency.mean_ms, cand.latency.mean_ms),
+            base.latency.p99_ms,
+            cand.latency.p99_ms,
+            percent_change(base.latency.p99_ms, cand.latency.p99_ms),
+            cand.throughput_qps,
+            percent_change(base.throughput_qps, cand.throughput_qps),
+            t,
+            match verdict {
+                Verdict::Ok => "ok",
+                Verdict::Improved => "improved",
+                Verdict::Regression => "REGRESSION",
+            }
+        );
+    }
+
+    for base in &baseline.queries {
+        let present = candidate.queries.iter().any(|c| query_key(c) == query_key(base));
+        if !present {
+            println!("{:<14} | {:<30} | {:<26} | (missing from candidate)", base.backend, base.name, base.mode_label());
+        }
+    }
+
+    println!("{:-<196}", "");
+    if regressions > 0 {
+        println!("{} significant regression(s) above {:.1}%.", regressions, args.threshold);
+    } else {
+        println!("No significant regressions.");
+    }
+    Ok(regressions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::LatencyStats;
+
+    fn args() -> CompareArgs {
+        CompareArgs { baseline: PathBuf::new(), candidate: PathBuf::new(), threshold: 10.0, significance: 1.96 }
+    }
+
+    fn query(mean_ms: f64, stddev_ms: f64, throughput_qps: f64) -> QueryReport {
+        let latency = LatencyStats {
+            samples: 100,
+            min_ms: mean_ms,
+            max_ms: mean_ms,
+            mean_ms,
+            stddev_ms,
+            p50_ms: mean_ms,
+            p90_ms: mean_ms,
+            p99_ms: mean_ms,
+            p999_ms: mean_ms,
+        };
+        let mut report = QueryReport::sequential(Backend::Postgres, "q", 1, latency);
+        report.throughput_qps = throughput_qps;
+        report
+    }
+
+    #[test]
+    fn zero_variance_with_equal_means_is_not_significant() {
+        let (base, cand) = (query(5.0, 0.0, 200.0), query(5.0, 0.0, 200.0));
+        assert_eq!(welch_t(&base, &cand), 0.0);
+        assert_eq!(judge(&base, &cand, &args()), (Verdict::Ok, 0.0));
+    }
+
+    #[test]
+    fn zero_variance_with_a_slower_mean_is_infinitely_significant() {
+        let (base, cand) = (query(5.0, 0.0, 200.0), query(6.0, 0.0, 160.0));
+        assert_eq!(welch_t(&base, &cand), f64::INFINITY);
+        assert_eq!(welch_t(&cand, &base), f64::NEG_INFINITY);
+        assert_eq!(judge(&base, &cand, &args()).0, Verdict::Regression);
+    }
+
+    #[test]
+    fn throughput_drop_without_a_latency_change_is_ok() {
+        // Concurrent runs: fewer requests went through, but each one was as fast as before
+        let (base, cand) = (query(5.0, 1.0, 1000.0), query(5.0, 1.0, 500.0));
+        assert_eq!(judge(&base, &cand, &args()).0, Verdict::Ok);
+    }
+
+    #[test]
+    fn throughput_drop_with_a_small_significant_slowdown_is_a_regression() {
+        // +4% latency is below the threshold, -30% throughput is not
+        let (base, cand) = (query(5.0, 0.5, 1000.0), query(5.2, 0.5, 700.0));
+        let (verdict, t) = judge(&base, &cand, &args());
+        assert!(t > 1.96);
+        assert_eq!(verdict, Verdict::Regression);
+    }
+
+    #[test]
+    fn significant_slowdown_below_the_threshold_is_ok() {
+        let (base, cand) = (query(5.0, 0.5, 200.0), query(5.3, 0.5, 190.0));
+        let (verdict, t) = judge(&base, &cand, &args());
+        assert!(t > 1.96);
+        assert_eq!(verdict, Verdict::Ok);
+    }
+
+    #[test]
+    fn significant_slowdown_above_the_threshold_is_a_regression() {
+        let (base, cand) = (query(5.0, 0.5, 200.0), query(6.0, 0.5, 166.0));
+        let (verdict, t) = judge(&base, &cand, &args());
+        assert!(t > 1.96);
+        assert_eq!(verdict, Verdict::Regression);
+    }
+
+    #[test]
+    fn noisy_slowdown_above_the_threshold_is_not_significant() {
+        let (base, cand) = (query(5.0, 20.0, 200.0), query(6.0, 20.0, 166.0));
+        let (verdict, t) = judge(&base, &cand, &args());
+        assert!(t < 1.96);
+        assert_eq!(verdict, Verdict::Ok);
+    }
+
+    #[test]
+    fn significant_speedup_above_the_threshold_is_an_improvement() {
+        let (base, cand) = (query(6.0, 0.5, 166.0), query(5.0, 0.5, 200.0));
+        assert_eq!(judge(&base, &cand, &args()).0, Verdict::Improved);
+    }
+}
@@ -0,0 +1,265 @@
+// src/concurrent.rs
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use elasticsearch::Elasticsearch;
+use rand::distr::{Distribution, weighted::WeightedIndex};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use tokio::task::JoinSet;
+use tracing::warn;
+
+use crate::report::{Backend, QueryReport};
+use crate::stats::{self, LatencyRecorder, LatencyStats};
+use crate::workload::{EsQuerySpec, QuerySpec, QueryVariant, Workload};
+use crate::BenchmarkError;
+use crate::cli::BenchArgs;
+use crate::elastic::run_es_query;
+use crate::postgres::{connect_postgres, prepare_pg_queries, run_pg_query};
+
+// Report entry name for the aggregate over the whole query mix
+pub const ALL_QUERIES: &str = "(all queries)";
+
+// When the concurrent load run stops
+#[derive(Debug, Clone, Copy)]
+pub enum LoadLimit {
+    Duration(Duration),
+    Requests(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LoadSettings {
+    pub workers: usize,
+    pub limit: LoadLimit,
+    // Also mix in the count variant of every query
+    pub count_totals: bool,
+}
+
+impl From<&BenchArgs> for LoadSettings {
+    fn from(args: &BenchArgs) -> Self {
+        let limit = match args.requests {
+            Some(requests) => LoadLimit::Requests(requests),
+            None => LoadLimit::Duration(Duration::from_secs(args.duration_secs)),
+        };
+        Self { workers: args.workers as usize, limit, count_totals: args.count_totals }
+    }
+}
+
+// Shared stop condition for all workers of one run
+struct StopCondition {
+    limit: LoadLimit,
+    started: Instant,
+    issued: AtomicUsize,
+}
+
+impl StopCondition {
+    fn new(limit: LoadLimit) -> Self {
+        Self { limit, started: Instant::now(), issued: AtomicUsize::new(0) }
+    }
+
+    // Claims the next request slot, returning false once the run is over
+    fn next_request(&self) -> bool {
+        match self.limit {
+            LoadLimit::Duration(duration) => self.started.elapsed() < duration,
+            LoadLimit::Requests(total) => self.issued.fetch_add(1, Ordering::Relaxed) < total,
+        }
+    }
+}
+
+// Per-worker results, one latency recorder per query in the mix
+struct WorkerResult {
+    recorders: Vec<LatencyRecorder>,
+    last_counts: Vec<usize>,
+    errors: Vec<usize>,
+}
+
+impl WorkerResult {
+    fn new(query_count: usize) -> Self {
+        Self {
+            recorders: (0..query_count).map(|_| LatencyRecorder::new()).collect(),
+            last_counts: vec![0; query_count],
+            errors: vec![0; query_count],
+        }
+    }
+
+    fn merge(&mut self, other: WorkerResult) {
+        for (recorder, other_recorder) in self.recorders.iter_mut().zip(&other.recorders) {
+            recorder.merge(other_recorder);
+        }
+        for (count, other_count) in self.last_counts.iter_mut().zip(other.last_counts) {
+            *count = (*count).max(other_count);
+        }
+        for (errors, other_errors) in self.errors.iter_mut().zip(other.errors) {
+            *errors += other_errors;
+        }
+    }
+}
+
+// Weighted query picker; each worker owns one so no locking is needed. Its RNG also draws the
+// query parameters.
+struct QueryPicker {
+    rng: StdRng,
+    weights: WeightedIndex<u32>,
+}
+
+impl QueryPicker {
+    fn new(weights: &[u32]) -> Result<Self, BenchmarkError> {
+        let weights = WeightedIndex::new(weights).map_err(|e| {
+            BenchmarkError::Workload(format!("invalid query weights for concurrent mode: {}", e))
+        })?;
+        Ok(Self { rng: StdRng::from_os_rng(), weights })
+    }
+
+    fn next(&mut self) -> usize {
+        self.weights.sample(&mut self.rng)
+    }
+}
+
+pub async fn load_test_postgres(table: &str, workload: &Workload, settings: &LoadSettings) -> Result<Vec<QueryReport>, BenchmarkError> {
+    if settings.workers == 0 {
+        return Ok(Vec::new());
+    }
+
+    // Every worker gets its own connection so requests actually contend on the server
+    let mut workers_state = Vec::with_capacity(settings.workers);
+    for _ in 0..settings.workers {
+        let client = connect_postgres().await?;
+        let queries = prepare_pg_queries(&client, table, workload, settings.count_totals).await?;
+        if queries.is_empty() {
+            return Ok(Vec::new());
+        }
+        let weights: Vec<u32> = queries.iter().map(|q| q.weight).collect();
+        workers_state.push((client, queries, QueryPicker::new(&weights)?));
+    }
+    let query_names: Vec<String> = workers_state[0].1.iter().map(|q| q.name.clone()).collect();
+
+    let stop = Arc::new(StopCondition::new(settings.limit));
+    let mut workers = JoinSet::new();
+    for (client, queries, mut picker) in workers_state {
+        let stop = Arc::clone(&stop);
+        workers.spawn(async move {
+            let mut result = WorkerResult::new(queries.len());
+            while stop.next_request() {
+                let next = picker.next();
+                let start = Instant::now();
+                match run_pg_query(&client, &queries[next], &mut picker.rng).await {
+                    Ok(count) => {
+                        result.recorders[next].record(start.elapsed());
+                        result.last_counts[next] = count;
+                    }
+                    Err(e) => {
+                        warn!("PostgreSQL query '{}' failed: {}", queries[next].name, e);
+                        result.errors[next] += 1;
+                    }
+                }
+            }
+            result
+        });
+    }
+
+    let result = collect_workers(workers, query_names.len()).await;
+    Ok(load_results(Backend::Postgres, &query_names, &result, stop.started.elapsed()))
+}
+
+pub async fn load_test_elasticsearch(client: &Elasticsearch, index: &str, workload: &Workload, settings: &LoadSettings) -> Result<Vec<QueryReport>, BenchmarkError> {
+    let queries: Arc<Vec<(QueryVariant, QuerySpec, EsQuerySpec)>> = Arc::new(
+        workload.es_variants(settings.count_totals).map(|(variant, spec, es)| (variant, spec.clone(), es.clone())).collect()
+    );
+    if queries.is_empty() || settings.workers == 0 {
+        return Ok(Vec::new());
+    }
+    let weights: Vec<u32> = queries.iter().map(|(_, spec, _)| spec.weight).collect();
+    let query_names: Vec<String> = queries.iter().map(|(variant, spec, _)| variant.query_name(&spec.name)).collect();
+    let limit = workload.limit;
+    let index = Arc::new(index.to_string());
+
+    let stop = Arc::new(StopCondition::new(settings.limit));
+    let mut workers = JoinSet::new();
+    for _ in 0..settings.workers {
+        // The client shares its connection pool between clones
+        let client = client.clone();
+        let queries = Arc::clone(&queries);
+        let stop = Arc::clone(&stop);
+        let index = Arc::clone(&index);
+        let mut picker = QueryPicker::new(&weights)?;
+        workers.spawn(async move {
+            let mut result = WorkerResult::new(queries.len());
+            while stop.next_request() {
+                let next = picker.next();
+                let (variant, spec, es) = &queries[next];
+                let es_query_json = spec.es_query(es, &spec.sample_params(&mut picker.rng));
+                let start = Instant::now();
+                match run_es_query(&client, &index, &spec.name, &es_query_json, limit, *variant).await {
+                    Ok(count) => {
+                        result.recorders[next].record(start.elapsed());
+                        result.last_counts[next] = count;
+                    }
+                    Err(e) => {
+                        warn!("Elasticsearch query failed: {}", e);
+                        result.errors[next] += 1;
+                    }
+                }
+            }
+            result
+        });
+    }
+
+    let result = collect_workers(workers, queries.len()).await;
+    Ok(load_results(Backend::Elasticsearch, &query_names, &result, stop.started.elapsed()))
+}
+
+async fn collect_workers(mut workers: JoinSet<WorkerResult>, query_count: usize) -> WorkerResult {
+    let mut combined = WorkerResult::new(query_count);
+    while let Some(joined) = workers.join_next().await {
+        match joined {
+            Ok(result) => combined.merge(result),
+            Err(e) => warn!("Load worker panicked: {}", e),
+        }
+    }
+    combined
+}
+
+// Print the per-query table and throughput line, and turn the merged results into report entries
+fn load_results(backend: Backend, query_names: &[String], result: &WorkerResult, elapsed: Duration) -> Vec<QueryReport> {
+    stats::print_stats_header();
+
+    let qps = |latency: &LatencyStats| {
+        if elapsed.is_zero() { 0.0 } else { latency.samples as f64 / elapsed.as_secs_f64() }
+    };
+    let query_report = |name: &str, result_count: usize, errors: usize, latency: LatencyStats| QueryReport {
+        backend,
+        name: name.to_string(),
+        mode: "concurrent".to_string(),
+        result_count,
+        errors,
+        throughput_qps: qps(&latency),
+        latency,
+        index_profile: None,
+    };
+
+    let mut reports = Vec::with_capacity(query_names.len() + 1);
+    let mut overall = LatencyRecorder::new();
+    for (i, query_name) in query_names.iter().enumerate() {
+        let latency = result.recorders[i].stats();
+        stats::print_stats_row(query_name, result.last_counts[i], &latency);
+        overall.merge(&result.recorders[i]);
+        reports.push(query_report(query_name, result.last_counts[i], result.errors[i], latency));
+    }
+
+    let overall_stats = overall.stats();
+    let total_count = result.last_counts.iter().sum();
+    let total_errors = result.errors.iter().sum();
+    println!("{:-<150}", "");
+    stats::print_stats_row(ALL_QUERIES, total_count, &overall_stats);
+    println!(
+        "{} Throughput: {:.2} req/s ({} requests in {:?}, {} errors)",
+        backend.label(),
+        qps(&overall_stats),
+        overall_stats.samples,
+        elapsed,
+        total_errors
+    );
+
+    reports.push(query_report(ALL_QUERIES, total_count, total_errors, overall_stats));
+    reports
+}
@@ -0,0 +1,971 @@
+// src/elastic.rs
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use elasticsearch::{
+    Elasticsearch, http::transport::Transport, cluster::ClusterHealthParts, params::WaitForStatus, SearchParts, CountParts, ScrollParts, ClearScrollParts,
+    BulkParts, DeleteByQueryParts, DeleteParts, GetParts, UpdateParts, http::response::Response, params::Conflicts,
+    indices::{
+        IndicesExistsParts, IndicesCreateParts, IndicesDeleteParts, IndicesRefreshParts,
+        IndicesStatsParts, IndicesSegmentsParts, IndicesGetMappingParts,
+        IndicesGetSettingsParts, IndicesPutSettingsParts, IndicesForcemergeParts,
+    },
+};
+use bytes::Bytes;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde_json::{Value, json};
+use tokio::task::JoinSet;
+use tracing::{debug, error, info, warn};
+
+use crate::BenchmarkError;
+use crate::cli::{BenchArgs, DataArgs, ExistingMode};
+use crate::report::{Backend, QueryReport, StorageReport};
+use crate::stats::{self, LatencyRecorder, LatencyStats};
+use crate::stream::{self, DocumentStream};
+use crate::verify;
+use crate::workload::{QueryVariant, Workload};
+use crate::writes::{WriteInput, WriteOp};
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+// --- Connection ---
+
+pub fn connect_elasticsearch() -> Result<Elasticsearch, BenchmarkError> {
+    let transport = Transport::single_node(
+        &env::var("ELASTICSEARCH_URL")
+            .unwrap_or_else(|_| "http://localhost:9200".to_string())
+    )?;
+    Ok(Elasticsearch::new(transport))
+}
+
+// Server versions are informational only, so failures are ignored
+pub async fn elasticsearch_version(client: &Elasticsearch) -> Option<String> {
+    let response = client.info().send().await.ok()?;
+    let body: Value = response.json().await.ok()?;
+    body["version"]["number"].as_str().map(str::to_string)
+}
+
+pub async fn count_documents(client: &Elasticsearch, index: &str) -> Result<usize, BenchmarkError> {
+    let response = client.count(CountParts::Index(&[index])).send().await?;
+    let body: Value = response.json().await?;
+    Ok(body["count"].as_u64().unwrap_or(0) as usize)
+}
+
+// --- Setup ---
+
+// Mapping the index is created with; fields not listed here are mapped dynamically
+fn index_mapping() -> Value {
+    json!({
+        "properties": {
+            // Also the _id; matched exactly, never analyzed
+            "id": { "type": "keyword" },
+            "title": { "type": "text" },
+            "content": { "type": "text" },
+            "created_at": { "type": "date" },
+            // Index tags as keyword for exact matching, filtering, aggregations
+            "tags": { "type": "keyword" },
+            // Index attributes as an object. Dynamic mapping will handle sub-fields.
+            // For production, you might explicitly map known attributes
+            // (e.g., "att0": {"type": "integer"}) for better control.
+            "attributes": {
+                "type": "object",
+                // "enabled": true // default is true
+                "properties": {
+                    "att0": { "type": "integer" }, // Explicitly map known numeric field
+                    "att1": { "type": "text", "fields": { "keyword": { "type": "keyword", "ignore_above": 256 }}}, // Text + keyword
+                    "att2": { "type": "object", "enabled": true }, // Allow dynamic mapping within att2
+                    "att3": { "type": "keyword" } // Array of strings often best as keyword
+                    // Optional attributes (att_opt_*) will be dynamically mapped
+                }
+            }
+        }
+    })
+}
+
+pub async fn setup_elasticsearch(client: &Elasticsearch, index: &str, existing: ExistingMode) -> Result<(), BenchmarkError> {
+    if existing == ExistingMode::Recreate {
+        drop_elasticsearch(client, index).await?;
+    }
+
+    let index_exists = client
+        .indices()
+        .exists(IndicesExistsParts::Index(&[index]))
+        .send()
+        .await?
+        .status_code()
+        .is_success();
+
+    if !index_exists {
+        info!("Creating Elasticsearch index '{}' with new mapping...", index);
+        let create_response = client
+            .indices()
+            .create(IndicesCreateParts::Index(index))
+            .body(json!({ "mappings": index_mapping() }))
+            .send()
+            .await?;
+
+        if !create_response.status_code().is_success() {
+            let response_body = create_response.text().await?;
+            error!("Failed to create index '{}': {}", index, response_body);
+            return Err(BenchmarkError::EsBulkError(format!(
+                "Failed to create index '{}'", index
+            )));
+        }
+         info!("Elasticsearch index '{}' created.", index);
+    } else {
+        // Data loaded into a drifted mapping would not be comparable with earlier runs
+        verify_mapping(client, index).await?;
+        if existing == ExistingMode::Truncate {
+            truncate_elasticsearch(client, index).await?;
+        } else {
+            info!("Elasticsearch index '{}' already exists, reusing it.", index);
+        }
+    }
+    Ok(())
+}
+
+// Fails with a field-by-field diff when the mapping of an existing index differs from `index_mapping`
+async fn verify_mapping(client: &Elasticsearch, index: &str) -> Result<(), BenchmarkError> {
+    let response = client.indices().get_mapping(IndicesGetMappingParts::Index(&[index])).send().await?;
+    let body = es_get_json(response, "_mapping").await?;
+    let mut diff = Vec::new();
+    diff_mapping("", &index_mapping(), &body[index]["mappings"], &mut diff);
+    if diff.is_empty() {
+        info!("Elasticsearch index '{}' mapping matches the expected mapping.", index);
+        return Ok(());
+    }
+    for line in &diff {
+        error!("Mapping of '{}': {}", index, line);
+    }
+    Err(BenchmarkError::Schema(format!(
+        "mapping of Elasticsearch index '{}' differs from the expected one in {} place(s); use --existing recreate",
+        index, diff.len()
+    )))
+}
+
+// Collects every expected mapping entry the actual mapping lacks or has with another value. Extra
+// fields in the actual mapping are dynamic mappings and are fine; Elasticsearch leaves out
+// `"type": "object"` and `"enabled": true`, so those count as present.
+fn diff_mapping(path: &str, expected: &Value, actual: &Value, diff: &mut Vec<String>) {
+    let Some(expected) = expected.as_object() else {
+        if expected != actual {
+            diff.push(format!("{}: expected {}, found {}", path, expected, actual));
+        }
+        return;
+    };
+    for (key, expected_value) in expected {
+        let field = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+        match actual.get(key) {
+            Some(actual_value) => diff_mapping(&field, expected_value, actual_value, diff),
+            None if (key == "type" && expected_value == "object") || (key == "enabled" && expected_value == true) => {}
+            None => diff.push(format!("{}: expected {}, missing", field, expected_value)),
+        }
+    }
+}
+
+// Deletes every document but keeps the index with its mapping and settings
+async fn truncate_elasticsearch(client: &Elasticsearch, index: &str) -> Result<(), BenchmarkError> {
+    let response = client
+        .delete_by_query(DeleteByQueryParts::Index(&[index]))
+        .body(json!({ "query": { "match_all": {} } }))
+        .conflicts(Conflicts::Proceed)
+        .refresh(true)
+        .send()
+        .await?;
+    let body = es_get_json(response, "_delete_by_query").await?;
+    info!("Elasticsearch index '{}' truncated ({} documents deleted).", index, body["deleted"].as_u64().unwrap_or(0));
+    Ok(())
+}
+
+pub async fn drop_elasticsearch(client: &Elasticsearch, index: &str) -> Result<(), BenchmarkError> {
+    let response = client.indices().delete(IndicesDeleteParts::Index(&[index])).send().await?;
+    // A missing index is fine, there is nothing to drop
+    if response.status_code().is_success() || response.status_code().as_u16() == 404 {
+        info!("Elasticsearch index '{}' dropped.", index);
+        Ok(())
+    } else {
+        let status = response.status_code();
+        let body = response.text().await?;
+        Err(BenchmarkError::EsBulkError(format!(
+            "Failed to delete index '{}' - Status: {}, Body: {}", index, status, body
+        )))
+    }
+}
+
+// --- Ingest Settings ---
+
+// Segments left per shard by the force-merge that follows a load with ingest settings
+const FORCE_MERGE_SEGMENTS: i64 = 1;
+
+// The index settings changed for bulk loading, as they were before the load. Unset values are kept
+// as null, which resets them to the cluster default when restored.
+pub struct IndexSettings {
+    refresh_interval: Value,
+    number_of_replicas: Value,
+    translog_durability: Value,
+}
+
+// Switches off refreshes, replicas and per-request translog fsyncs for the duration of a bulk load;
+// returns the previous settings for `restore_index_settings`
+pub async fn apply_ingest_settings(client: &Elasticsearch, index: &str) -> Result<IndexSettings, BenchmarkError> {
+    let response = client.indices().get_settings(IndicesGetSettingsParts::Index(&[index])).send().await?;
+    let body = es_get_json(response, "_settings").await?;
+    let current = &body[index]["settings"]["index"];
+    let previous = IndexSettings {
+        refresh_interval: current["refresh_interval"].clone(),
+        number_of_replicas: current["number_of_replicas"].clone(),
+        translog_durability: current["translog"]["durability"].clone(),
+    };
+
+    info!("Applying ingest settings to Elasticsearch index '{}' (no refresh, no replicas, async translog)...", index);
+    put_index_settings(client, index, json!({
+        "index": {
+            "refresh_interval": "-1",
+            "number_of_replicas": 0,
+            "translog": { "durability": "async" }
+        }
+    })).await?;
+    Ok(previous)
+}
+
+pub async fn restore_index_settings(client: &Elasticsearch, index: &str, previous: &IndexSettings) -> Result<(), BenchmarkError> {
+    info!("Restoring Elasticsearch index settings of '{}'...", index);
+    put_index_settings(client, index, json!({
+        "index": {
+            "refresh_interval": previous.refresh_interval,
+            "number_of_replicas": previous.number_of_replicas,
+            "translog": { "durability": previous.translog_durability }
+        }
+    })).await?;
+    wait_for_allocation(client, index).await
+}
+
+// How long to wait for restored replicas to be allocated and recovered
+const ALLOCATION_TIMEOUT: &str = "10m";
+
+// Waits until no shard of the index is initializing, i.e. the restored replicas are allocated and
+// recovered. Replicas that cannot be allocated at all (single-node clusters) stay unassigned and are not waited for.
+async fn wait_for_allocation(client: &Elasticsearch, index: &str) -> Result<(), BenchmarkError> {
+    let response = client
+        .cluster()
+        .health(ClusterHealthParts::Index(&[index]))
+        .wait_for_status(WaitForStatus::Yellow)
+        .wait_for_no_initializing_shards(true)
+        .timeout(ALLOCATION_TIMEOUT)
+        .send()
+        .await?;
+    // A timeout comes back as 408 with the health as it was at that point
+    let body: Value = if response.status_code().as_u16() == 408 {
+        response.json().await?
+    } else {
+        es_get_json(response, "_cluster/health").await?
+    };
+    if body["timed_out"].as_bool() == Some(true) {
+        warn!("Elasticsearch index '{}' still has {} initializing shard(s) after {}", index, body["initializing_shards"], ALLOCATION_TIMEOUT);
+    } else {
+        info!(
+            "Elasticsearch index '{}' is {} ({} unassigned shard(s))",
+            index, body["status"].as_str().unwrap_or("unknown"), body["unassigned_shards"]
+        );
+    }
+    Ok(())
+}
+
+async fn put_index_settings(client: &Elasticsearch, index: &str, settings: Value) -> Result<(), BenchmarkError> {
+    let response = client.indices().put_settings(IndicesPutSettingsParts::Index(&[index])).body(settings).send().await?;
+    es_get_json(response, "_settings").await?;
+    Ok(())
+}
+
+// Merges the segments a bulk load leaves behind, as a production index would be before serving queries
+pub async fn force_merge(client: &Elasticsearch, index: &str) -> Result<(), BenchmarkError> {
+    info!("Force-merging Elasticsearch index '{}' to {} segment(s)...", index, FORCE_MERGE_SEGMENTS);
+    let response = client
+        .indices()
+        .forcemerge(IndicesForcemergeParts::Index(&[index]))
+        .max_num_segments(FORCE_MERGE_SEGMENTS)
+        .send()
+        .await?;
+    es_get_json(response, "_forcemerge").await?;
+    Ok(())
+}
+
+// --- Storage ---
+
+// Store size, document and segment counts of the index from the _stats, _segments and _mapping APIs
+pub async fn storage_report(client: &Elasticsearch, index: &str) -> Result<StorageReport, BenchmarkError> {
+    let stats = es_get_json(client.indices().stats(IndicesStatsParts::Index(&[index])).send().await?, "_stats").await?;
+    let index_stats = &stats["indices"][index];
+    let primaries = &index_stats["primaries"];
+
+    let mut metrics = BTreeMap::new();
+    let mut put = |name: &str, value: &Value| {
+        metrics.insert(name.to_string(), value.as_u64().unwrap_or(0));
+    };
+    put("store_bytes", &primaries["store"]["size_in_bytes"]);
+    put("total_store_bytes", &index_stats["total"]["store"]["size_in_bytes"]);
+    put("deleted_docs", &primaries["docs"]["deleted"]);
+    put("segments", &primaries["segments"]["count"]);
+    put("fielddata_bytes", &primaries["fielddata"]["memory_size_in_bytes"]);
+
+    // Segment-level view of the primaries: how much of the store is already committed to disk
+    let segments = es_get_json(client.indices().segments(IndicesSegmentsParts::Index(&[index])).send().await?, "_segments").await?;
+    let (mut committed, mut searchable, mut segment_bytes) = (0, 0, 0);
+    for copies in segments["indices"][index]["shards"].as_object().into_iter().flat_map(|s| s.values()) {
+        for copy in copies.as_array().into_iter().flatten().filter(|c| c["routing"]["primary"].as_bool() == Some(true)) {
+            committed += copy["num_committed_segments"].as_u64().unwrap_or(0);
+            searchable += copy["num_search_segments"].as_u64().unwrap_or(0);
+            for segment in copy["segments"].as_object().into_iter().flat_map(|s| s.values()) {
+                segment_bytes += segment["size_in_bytes"].as_u64().unwrap_or(0);
+            }
+        }
+    }
+    metrics.insert("committed_segments".to_string(), committed);
+    metrics.insert("search_segments".to_string(), searchable);
+    metrics.insert("segments_bytes".to_string(), segment_bytes);
+
+    // Dynamic mapping of att_opt_* and att2 grows the mapping; track its field count and size
+    let mapping = es_get_json(client.indices().get_mapping(IndicesGetMappingParts::Index(&[index])).send().await?, "_mapping").await?;
+    let mappings = &mapping[index]["mappings"];
+    metrics.insert("mapping_fields".to_string(), count_mapped_fields(&mappings["properties"]));
+    metrics.insert("mapping_bytes".to_string(), mappings.to_string().len() as u64);
+
+    let total_bytes = metrics["store_bytes"];
+    info!(
+        "Elasticsearch index '{}' storage: {:.1} MiB primary store, {} segments, {} mapped fields",
+        index, total_bytes as f64 / MIB, metrics["segments"], metrics["mapping_fields"]
+    );
+    Ok(StorageReport {
+        backend: Backend::Elasticsearch,
+        name: index.to_string(),
+        documents: primaries["docs"]["count"].as_u64().unwrap_or(0),
+        total_bytes,
+        metrics,
+    })
+}
+
+async fn es_get_json(response: Response, api: &str) -> Result<Value, BenchmarkError> {
+    if !response.status_code().is_success() {
+        let status = response.status_code();
+        let body = response.text().await?;
+        return Err(BenchmarkError::EsQuery(format!("{} - Status: {}, Body: {}", api, status, body)));
+    }
+    Ok(response.json().await?)
+}
+
+fn count_mapped_fields(properties: &Value) -> u64 {
+    properties.as_object().map_or(0, |fields| {
+        fields.values().map(|field| {
+            let sub_fields = field["fields"].as_object().map_or(0, |f| f.len() as u64);
+            1 + sub_fields + count_mapped_fields(&field["properties"])
+        }).sum()
+    })
+}
+
+// --- Insertion ---
+
+// How documents are grouped into bulk requests, how many may be in flight and how failures are handled
+#[derive(Clone)]
+pub struct BulkSettings {
+    pub batch_size: usize,
+    // Upper bound on the NDJSON bytes of one request; a batch is flushed early rather than go over it
+    pub batch_bytes: Option<usize>,
+    pub concurrency: usize,
+    pub max_retries: u32,
+    pub retry_backoff: Duration,
+    // Abort the load once more documents than this have been rejected for good
+    pub max_failed_docs: usize,
+    pub dead_letter: PathBuf,
+}
+
+impl From<&DataArgs> for BulkSettings {
+    fn from(args: &DataArgs) -> Self {
+        Self {
+            batch_size: args.batch_size.max(1),
+            batch_bytes: args.batch_bytes,
+            concurrency: args.es_concurrency as usize,
+            max_retries: args.es_max_retries,
+            retry_backoff: Duration::from_millis(args.es_retry_backoff_ms),
+            max_failed_docs: args.es_max_failed_docs,
+            dead_letter: args.dead_letter.clone(),
+        }
+    }
+}
+
+// Outcome of a bulk load
+pub struct BulkResult {
+    pub documents: usize,
+    pub failed: usize,
+    pub retries: usize,
+    pub batches: usize,
+    pub refresh: Duration,
+    pub request_latency: LatencyStats,
+}
+
+// Statuses Elasticsearch uses for back pressure; items rejected with these are sent again
+const RETRYABLE_STATUSES: [u16; 2] = [429, 503];
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+// A document Elasticsearch refused for good
+struct RejectedItem {
+    item: Bytes,
+    status: u16,
+    error: Value,
+}
+
+// Outcome of one batch, including its retries
+#[derive(Default)]
+struct BatchOutcome {
+    indexed: usize,
+    retries: usize,
+    latencies: Vec<Duration>,
+    rejected: Vec<RejectedItem>,
+}
+
+// Action and source lines of one document, serialized once so batches can be sized in bytes
+fn bulk_item(doc: &Value) -> Result<Bytes, BenchmarkError> {
+    // The document id becomes the _id, so PostgreSQL rows and Elasticsearch documents correlate. `create`
+    // rejects an id that is already indexed (409) instead of overwriting it, the way the unique doc_id
+    // index rejects it in PostgreSQL.
+    let mut item = match doc["id"].as_str() {
+        Some(id) => serde_json::to_vec(&json!({ "create": { "_id": id } }))?,
+        None => br#"{"create":{}}"#.to_vec(),
+    };
+    item.push(b'\n');
+    serde_json::to_writer(&mut item, doc)?;
+    item.push(b'\n');
+    Ok(Bytes::from(item))
+}
+
+// Sends one batch, retrying items (or the whole request) rejected with 429/503 with
+// exponential backoff; whatever still fails is returned as rejected
+async fn send_bulk(client: &Elasticsearch, index: &str, items: Vec<Bytes>, settings: &BulkSettings) -> Result<BatchOutcome, BenchmarkError> {
+    let mut outcome = BatchOutcome::default();
+    let mut pending = items;
+    let mut attempt = 0;
+    loop {
+        let start = Instant::now();
+        let response = client
+            .bulk(BulkParts::Index(index))
+            .body(pending.clone())
+            .send()
+            .await?;
+        outcome.latencies.push(start.elapsed());
+
+        let status = response.status_code().as_u16();
+        let can_retry = attempt < settings.max_retries;
+
+        let mut retry_items = Vec::new();
+        if RETRYABLE_STATUSES.contains(&status) {
+            // The whole request was pushed back
+            let error = Value::String(response.text().await?);
+            if can_retry {
+                retry_items = pending;
+            } else {
+                outcome.rejected.extend(pending.into_iter().map(|item| RejectedItem { item, status, error: error.clone() }));
+            }
+        } else if !response.status_code().is_success() {
+            let response_body_text = response.text().await?;
+            error!("Elasticsearch bulk insert failed with status {}: {}", status, response_body_text);
+            return Err(BenchmarkError::EsBulkError(format!(
+                "Bulk insert failed with status {} - Body: {}", status, response_body_text
+            )));
+        } else {
+            let response_body = response.json::<Value>().await?;
+            if response_body["errors"].as_bool() == Some(true) {
+                let results = response_body["items"].as_array().filter(|r| r.len() == pending.len()).ok_or_else(|| {
+                    BenchmarkError::EsBulkError("bulk response items do not match the request".to_string())
+                })?;
+                for (item, result) in pending.into_iter().zip(results) {
+                    // Each result is keyed by its action, e.g. {"create": {"status": 201, ...}}
+                    let result = result.as_object().and_then(|r| r.values().next()).unwrap_or(&Value::Null);
+                    let item_status = result["status"].as_u64().unwrap_or(0) as u16;
+                    if (200..300).contains(&item_status) {
+                        outcome.indexed += 1;
+                    } else if can_retry && RETRYABLE_STATUSES.contains(&item_status) {
+                        retry_items.push(item);
+                    } else {
+                        outcome.rejected.push(RejectedItem { item, status: item_status, error: result["error"].clone() });
+                    }
+                }
+            } else {
+                outcome.indexed += pending.len();
+            }
+        }
+
+        if retry_items.is_empty() {
+            return Ok(outcome);
+        }
+        attempt += 1;
+        outcome.retries += 1;
+        let backoff = settings.retry_backoff.saturating_mul(1 << (attempt - 1).min(16)).min(MAX_BACKOFF);
+        debug!("Retrying {} bulk items in {:?} (attempt {})", retry_items.len(), backoff, attempt);
+        tokio::time::sleep(backoff).await;
+        pending = retry_items;
+    }
+}
+
+// Rejected documents with their error, one JSON object per line; created on first use
+struct DeadLetter<'a> {
+    path: &'a Path,
+    writer: Option<BufWriter<File>>,
+    count: usize,
+}
+
+impl<'a> DeadLetter<'a> {
+    fn new(path: &'a Path) -> Self {
+        Self { path, writer: None, count: 0 }
+    }
+
+    fn write(&mut self, rejected: &RejectedItem) -> Result<(), BenchmarkError> {
+        if self.writer.is_none() {
+            self.writer = Some(BufWriter::new(File::create(self.path)?));
+        }
+        let writer = self.writer.as_mut().unwrap();
+        // The source is the second line of the bulk item
+        let source = rejected.item.split(|b| *b == b'\n').nth(1).unwrap_or_default();
+        let document: Value = serde_json::from_slice(source)?;
+        serde_json::to_writer(&mut *writer, &json!({ "status": rejected.status, "error": rejected.error, "document": document }))?;
+        writer.write_all(b"\n")?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), BenchmarkError> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.flush()?;
+            warn!("{} rejected document(s) written to {}", self.count, self.path.display());
+        }
+        Ok(())
+    }
+}
+
+// Streams documents into the index in bulk batches, with up to `concurrency` requests in flight
+pub async fn insert_elasticsearch_value(client: &Elasticsearch, index: &str, settings: &BulkSettings, mut docs: DocumentStream, expected_len: Option<u64>) -> Result<BulkResult, BenchmarkError> {
+    match settings.batch_bytes {
+        Some(bytes) => info!(
+            "Inserting documents into Elasticsearch in batches of {} docs or {} bytes, {} request(s) in flight...",
+            settings.batch_size, bytes, settings.concurrency
+        ),
+        None => info!(
+            "Inserting documents into Elasticsearch in batches of {}, {} request(s) in flight...",
+            settings.batch_size, settings.concurrency
+        ),
+    }
+    let pb = stream::progress_bar(expected_len);
+    let settings = Arc::new(settings.clone());
+
+    let mut in_flight = JoinSet::new();
+    let mut totals = BulkTotals::new(&settings.dead_letter);
+    let mut batches = 0;
+    let mut items: Vec<Bytes> = Vec::with_capacity(settings.batch_size);
+    let mut batch_bytes = 0;
+    loop {
+        let doc_value = docs.recv().await;
+        let done = doc_value.is_none();
+        let mut ready = Vec::new();
+        if let Some(doc_value) = doc_value {
+            let item = bulk_item(&doc_value?)?;
+            // Send what is batched first when this item would take the request over the byte limit; an
+            // item larger than the limit on its own still goes out, alone
+            if settings.batch_bytes.is_some_and(|max| !items.is_empty() && batch_bytes + item.len() > max) {
+                ready.push(std::mem::replace(&mut items, Vec::with_capacity(settings.batch_size)));
+                batch_bytes = 0;
+            }
+            batch_bytes += item.len();
+            items.push(item);
+        }
+        if items.len() >= settings.batch_size || (done && !items.is_empty()) {
+            ready.push(std::mem::replace(&mut items, Vec::with_capacity(settings.batch_size)));
+            batch_bytes = 0;
+        }
+
+        for batch in ready {
+            // Wait for a free slot; the producer is held back meanwhile
+            if in_flight.len() >= settings.concurrency {
+                totals.add(join_bulk(&mut in_flight).await?, &settings)?;
+            }
+            let count = batch.len();
+            let (client, index, pb, settings) = (client.clone(), index.to_string(), pb.clone(), settings.clone());
+            in_flight.spawn(async move {
+                let outcome = send_bulk(&client, &index, batch, &settings).await?;
+                pb.inc(count as u64);
+                pb.set_message(if outcome.rejected.is_empty() { "Batch successful." } else { "Batch completed with item errors." });
+                Ok::<_, BenchmarkError>(outcome)
+            });
+            batches += 1;
+        }
+        if done {
+            break;
+        }
+    }
+    while !in_flight.is_empty() {
+        totals.add(join_bulk(&mut in_flight).await?, &settings)?;
+    }
+    totals.dead_letter.finish()?;
+    pb.finish_with_message("Elasticsearch insertion complete");
+    if totals.failed > 0 {
+        warn!("Elasticsearch rejected {} document(s) after retries", totals.failed);
+    }
+
+    // Force a refresh
+    info!("Refreshing Elasticsearch index...");
+    let refresh_start = Instant::now();
+    client.indices().refresh(IndicesRefreshParts::Index(&[index])).send().await?;
+    let refresh_duration = refresh_start.elapsed();
+    info!("Elasticsearch refresh took: {:?}", refresh_duration);
+
+    Ok(BulkResult {
+        documents: totals.indexed,
+        failed: totals.failed,
+        retries: totals.retries,
+        batches,
+        refresh: refresh_duration,
+        request_latency: totals.recorder.stats(),
+    })
+}
+
+// Running totals over the finished batches of a load
+struct BulkTotals<'a> {
+    indexed: usize,
+    failed: usize,
+    retries: usize,
+    recorder: LatencyRecorder,
+    dead_letter: DeadLetter<'a>,
+}
+
+impl<'a> BulkTotals<'a> {
+    fn new(dead_letter: &'a Path) -> Self {
+        Self { indexed: 0, failed: 0, retries: 0, recorder: LatencyRecorder::new(), dead_letter: DeadLetter::new(dead_letter) }
+    }
+
+    // Fails the load once the rejected documents exceed the threshold
+    fn add(&mut self, outcome: BatchOutcome, settings: &BulkSettings) -> Result<(), BenchmarkError> {
+        self.indexed += outcome.indexed;
+        self.retries += outcome.retries;
+        for latency in outcome.latencies {
+            self.recorder.record(latency);
+        }
+        for rejected in &outcome.rejected {
+            self.dead_letter.write(rejected)?;
+        }
+        self.failed += outcome.rejected.len();
+        if self.failed > settings.max_failed_docs {
+            self.dead_letter.finish()?;
+            return Err(BenchmarkError::EsBulkError(format!(
+                "{} documents rejected, more than the allowed {} (see {})",
+                self.failed, settings.max_failed_docs, settings.dead_letter.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+async fn join_bulk(in_flight: &mut JoinSet<Result<BatchOutcome, BenchmarkError>>) -> Result<BatchOutcome, BenchmarkError> {
+    match in_flight.join_next().await {
+        Some(Ok(result)) => result,
+        Some(Err(e)) => Err(BenchmarkError::Ingest(format!("bulk request task panicked: {}", e))),
+        None => Ok(BatchOutcome::default()),
+    }
+}
+
+// --- Queries ---
+
+// Run a single ES query from the suite; returns the number of hits it produced, or the exact total for
+// count variants
+pub async fn run_es_query(client: &Elasticsearch, index: &str, query_desc: &str, es_query_json: &Value, limit: usize, variant: QueryVariant) -> Result<usize, BenchmarkError> {
+    let body = match variant {
+        QueryVariant::Page => json!({
+            "_source": ["title"], // Only fetch title
+            "query": es_query_json, // Use the provided JSON query structure
+            "size": limit
+        }),
+        // No hits, only an exact total instead of the default lower bound of 10,000
+        QueryVariant::Count => json!({
+            "query": es_query_json,
+            "size": 0,
+            "track_total_hits": true
+        }),
+    };
+    let response = client
+        .search(SearchParts::Index(&[index]))
+        .body(body)
+        .send()
+        .await?;
+
+    // Check HTTP status before parsing JSON
+    if !response.status_code().is_success() {
+        let status = response.status_code();
+        let error_body = response.text().await?;
+        return Err(BenchmarkError::EsQuery(format!(
+            "'{}' - Status: {}, Body: {}", query_desc, status, error_body
+        )));
+    }
+
+    let response_body: Value = response.json().await?;
+    Ok(match variant {
+        QueryVariant::Page => response_body["hits"]["hits"].as_array().map_or(0, |h| h.len()),
+        QueryVariant::Count => response_body["hits"]["total"]["value"].as_u64().unwrap_or(0) as usize,
+    })
+}
+
+// Page size of the scroll that fetches every match of a query
+const SCROLL_PAGE_SIZE: usize = 1_000;
+
+// Keys of every document matching a query rather than the first `limit` hits, with the total Elasticsearch
+// reports. Documents with an `id` are keyed by their _id without fetching the source; only documents
+// without one are fetched, and fingerprinted page by page.
+pub async fn matching_keys(client: &Elasticsearch, index: &str, query: &Value) -> Result<(u64, BTreeSet<String>), BenchmarkError> {
+    let mut keys = BTreeSet::new();
+    let with_id = json!({ "bool": { "filter": [query, { "exists": { "field": "id" } }] } });
+    let mut total = scroll_matches(client, index, &with_id, false, |hit| {
+        if let Some(id) = hit["_id"].as_str() {
+            keys.insert(id.to_string());
+        }
+    }).await?;
+    let without_id = json!({ "bool": { "filter": [query], "must_not": [{ "exists": { "field": "id" } }] } });
+    total += scroll_matches(client, index, &without_id, true, |hit| {
+        keys.insert(verify::fingerprint(&hit["_source"]));
+    }).await?;
+    Ok((total, keys))
+}
+
+// Scrolls through every hit of a query, handing each one to `visit` as its page arrives; returns the total
+async fn scroll_matches(client: &Elasticsearch, index: &str, query: &Value, source: bool, mut visit: impl FnMut(&Value)) -> Result<u64, BenchmarkError> {
+    let response = client
+        .search(SearchParts::Index(&[index]))
+        .scroll("1m")
+        .body(json!({
+            "_source": source,
+            "query": query,
+            "size": SCROLL_PAGE_SIZE,
+            "track_total_hits": true
+        }))
+        .send()
+        .await?;
+    let mut page = es_get_json(response, "_search").await?;
+    let total = page["hits"]["total"]["value"].as_u64().unwrap_or(0);
+
+    loop {
+        let hits = page["hits"]["hits"].as_array().map_or(&[][..], Vec::as_slice);
+        if hits.is_empty() {
+            break;
+        }
+        hits.iter().for_each(&mut visit);
+        let scroll_id = page["_scroll_id"].as_str().unwrap_or_default().to_string();
+        let response = client
+            .scroll(ScrollParts::None)
+            .body(json!({ "scroll": "1m", "scroll_id": scroll_id }))
+            .send()
+            .await?;
+        page = es_get_json(response, "_search/scroll").await?;
+    }
+
+    // Scroll contexts hold resources on the cluster until they expire, so release this one now
+    if let Some(scroll_id) = page["_scroll_id"].as_str() {
+        client.clear_scroll(ClearScrollParts::None).body(json!({ "scroll_id": [scroll_id] })).send().await?;
+    }
+    Ok(total)
+}
+
+// Title of a document, for logging the few documents a verification disagrees on
+pub async fn document_title(client: &Elasticsearch, index: &str, id: &str) -> Result<Option<String>, BenchmarkError> {
+    let response = client.get(GetParts::IndexId(index, id))._source_includes(&["title"]).send().await?;
+    if response.status_code().as_u16() == 404 {
+        return Ok(None);
+    }
+    let body = es_get_json(response, "_doc").await?;
+    Ok(body["_source"]["title"].as_str().map(str::to_string))
+}
+
+// --- Writes ---
+
+// Largest page a plain search may return (index.max_result_window)
+const MAX_RESULT_WINDOW: usize = 10_000;
+
+// Times an update re-reads and retries a document another writer changed in between, instead of
+// failing with a version conflict
+const RETRY_ON_CONFLICT: i64 = 3;
+
+// Runs one write and returns how many documents it changed
+pub async fn run_es_write(client: &Elasticsearch, index: &str, op: WriteOp, input: &WriteInput<'_>) -> Result<u64, BenchmarkError> {
+    let response = match op {
+        WriteOp::SetAttribute => client
+            .update(UpdateParts::IndexId(index, input.id))
+            .retry_on_conflict(RETRY_ON_CONFLICT)
+            .body(json!({ "doc": { "attributes": { "att0": input.value } } }))
+            .send()
+            .await?,
+        WriteOp::IncrementAttribute => client
+            .update(UpdateParts::IndexId(index, input.id))
+            .retry_on_conflict(RETRY_ON_CONFLICT)
+            .body(json!({
+                "script": {
+                    "lang": "painless",
+                    "source": "ctx._source.attributes.att0 += params.by",
+                    "params": { "by": input.value }
+                }
+            }))
+            .send()
+            .await?,
+        WriteOp::DeleteById => client.delete(DeleteParts::IndexId(index, input.id)).send().await?,
+        WriteOp::DeleteByQuery => client
+            .delete_by_query(DeleteByQueryParts::Index(&[index]))
+            .conflicts(Conflicts::Proceed)
+            .body(json!({ "query": { "term": { "attributes.att0": input.value } } }))
+            .send()
+            .await?,
+    };
+
+    // A document removed by an earlier write is not an error, the write just changes nothing
+    if op != WriteOp::DeleteByQuery && response.status_code().as_u16() == 404 {
+        return Ok(0);
+    }
+    let body = es_get_json(response, op.name()).await?;
+    Ok(match op {
+        WriteOp::DeleteByQuery => body["deleted"].as_u64().unwrap_or(0),
+        _ => u64::from(body["result"] != "noop"),
+    })
+}
+
+// Random document ids to run writes against
+pub async fn sample_doc_ids(client: &Elasticsearch, index: &str, count: usize) -> Result<Vec<String>, BenchmarkError> {
+    let response = client
+        .search(SearchParts::Index(&[index]))
+        .body(json!({
+            "_source": false,
+            "size": count.min(MAX_RESULT_WINDOW),
+            "query": { "function_score": { "query": { "match_all": {} }, "random_score": {} } }
+        }))
+        .send()
+        .await?;
+    let body = es_get_json(response, "_search").await?;
+    Ok(body["hits"]["hits"]
+        .as_array()
+        .map_or_else(Vec::new, |hits| hits.iter().filter_map(|hit| hit["_id"].as_str().map(str::to_string)).collect()))
+}
+
+pub async fn benchmark_elasticsearch(client: &Elasticsearch, index: &str, workload: &Workload, settings: &BenchArgs) -> Result<Vec<QueryReport>, BenchmarkError> {
+    info!("{} warmup + {} measured iterations per query", settings.warmup, settings.iterations);
+    stats::print_stats_header();
+
+    let mut total_mean_ms = 0.0;
+    let mut total_rows_found = 0;
+    let mut measured_count = 0;
+    let mut reports = Vec::new();
+
+    let mut rng = StdRng::from_os_rng();
+    'queries: for (variant, spec, es) in workload.es_variants(settings.count_totals) {
+        let name = variant.query_name(&spec.name);
+        for _ in 0..settings.warmup {
+            let query = spec.es_query(es, &spec.sample_params(&mut rng));
+            if let Err(e) = run_es_query(client, index, &name, &query, workload.limit, variant).await {
+                warn!("Elasticsearch query failed: {}", e);
+                continue 'queries; // Skip this query
+            }
+        }
+
+        let mut recorder = LatencyRecorder::new();
+        let mut hits = 0;
+        for _ in 0..settings.iterations {
+            let query = spec.es_query(es, &spec.sample_params(&mut rng));
+            let start = Instant::now();
+            match run_es_query(client, index, &name, &query, workload.limit, variant).await {
+                Ok(count) => hits = count,
+                Err(e) => {
+                    warn!("Elasticsearch query failed: {}", e);
+                    continue 'queries; // Skip this query
+                }
+            }
+            recorder.record(start.elapsed());
+        }
+
+        let query_stats = recorder.stats();
+        total_mean_ms += query_stats.mean_ms;
+        total_rows_found += hits;
+        measured_count += 1;
+        stats::print_stats_row(&name, hits, &query_stats);
+        reports.push(QueryReport::sequential(Backend::Elasticsearch, &name, hits, query_stats));
+    }
+
+    let avg_latency_ms = if measured_count > 0 { total_mean_ms / measured_count as f64 } else { 0.0 };
+    println!("{:-<150}", "");
+    println!(
+        "Elasticsearch Average Latency: {:.4}ms ({} queries, {} total results)",
+        avg_latency_ms,
+        measured_count,
+        total_rows_found
+    );
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Mapping as Elasticsearch returns it after a load: objects without "type", dynamic fields added
+    fn loaded_mapping() -> Value {
+        let dynamic_text = json!({ "type": "text", "fields": { "keyword": { "type": "keyword", "ignore_above": 256 } } });
+        json!({
+            "properties": {
+                "id": { "type": "keyword" },
+                "title": { "type": "text" },
+                "content": { "type": "text" },
+                "created_at": { "type": "date" },
+                "tags": { "type": "keyword" },
+                "attributes": {
+                    "properties": {
+                        "att0": { "type": "integer" },
+                        "att1": { "type": "text", "fields": { "keyword": { "type": "keyword", "ignore_above": 256 } } },
+                        "att2": {
+                            "properties": {
+                                "nested_bool": { "type": "boolean" },
+                                "nested_key": dynamic_text
+                            }
+                        },
+                        "att3": { "type": "keyword" },
+                        "att_opt_0": dynamic_text,
+                        "att_opt_1": dynamic_text
+                    }
+                }
+            }
+        })
+    }
+
+    fn diff(actual: &Value) -> Vec<String> {
+        let mut diff = Vec::new();
+        diff_mapping("", &index_mapping(), actual, &mut diff);
+        diff
+    }
+
+    #[test]
+    fn loaded_mapping_with_dynamic_fields_matches() {
+        assert_eq!(diff(&loaded_mapping()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn changed_field_type_is_reported() {
+        let mut actual = loaded_mapping();
+        actual["properties"]["attributes"]["properties"]["att0"]["type"] = json!("long");
+        assert_eq!(diff(&actual), ["properties.attributes.properties.att0.type: expected \"integer\", found \"long\""]);
+    }
+
+    #[test]
+    fn missing_field_is_reported() {
+        let mut actual = loaded_mapping();
+        actual["properties"].as_object_mut().unwrap().remove("tags");
+        assert_eq!(diff(&actual), ["properties.tags: expected {\"type\":\"keyword\"}, missing"]);
+    }
+
+    #[test]
+    fn disabled_object_is_reported() {
+        let mut actual = loaded_mapping();
+        actual["properties"]["attributes"]["properties"]["att2"]["enabled"] = json!(false);
+        assert_eq!(diff(&actual), ["properties.attributes.properties.att2.enabled: expected true, found false"]);
+    }
+}
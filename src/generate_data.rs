@@ -1,70 +1,194 @@
 // src/generate_data.rs
-use chrono::Utc;
-use fake::{locales::JA_JP, Fake, Faker}; // Use Faker for more diverse attribute values
-use rand::Rng;
-use serde_json::json; // Needed for creating the attributes object
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use fake::{locales::JA_JP, Fake};
+use fake::faker::address::raw::CityName;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use serde_json::{json, Value}; // Needed for creating the attributes object
 
 const MAX_TAGS: usize = 5;
 const MIN_TAGS: usize = 1;
-const MAX_ATTR_KEYS: usize = 4; // Corresponds to att0, att1, att2, att3
-
-pub async fn generate_documents(count: usize) -> Vec<String> {
-    let mut rng = rand::thread_rng();
-    let mut docs = Vec::with_capacity(count);
-
-    println!("Generating {} documents with tags and attributes...", count);
-    let pb = indicatif::ProgressBar::new(count as u64);
-    pb.set_style(indicatif::ProgressStyle::default_bar()
-        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
-        .unwrap()
-        .progress_chars("#>-"));
-
-    for i in 0..count {
-        let title = fake::vec![String as fake::faker::address::raw::CityName(JA_JP); 3..5].join(" ");
-        let content = fake::vec![String as fake::faker::address::raw::CityName(JA_JP); 5..50].join(" ");
-        let created_at = Utc::now() - chrono::Duration::days(rng.gen_range(0..365));
-
-        // Generate Tags
-        let num_tags = rng.gen_range(MIN_TAGS..=MAX_TAGS);
-        let tags: Vec<String> = (0..num_tags)
-            // Generate more realistic-looking tags (e.g., single words)
-            .map(|_| fake::faker::lorem::en::Word().fake::<String>().to_lowercase())
+
+// Everything that determines the generated corpus: the same settings always yield the same documents
+#[derive(Debug, Clone, Copy)]
+pub struct GenerationSettings {
+    pub count: usize,
+    pub seed: u64,
+    // created_at values are spread over the year before this instant
+    pub reference_time: DateTime<Utc>,
+}
+
+// Write the first `limit` documents of the corpus as a pretty-printed JSON array
+pub fn dump_samples(settings: &GenerationSettings, limit: usize, path: &Path) -> std::io::Result<usize> {
+    // Chunks only depend on their index, so a shorter corpus is an exact prefix of the full one
+    let sample_settings = GenerationSettings { count: limit.min(settings.count), ..*settings };
+    let samples: Vec<Value> = DocumentGenerator::new(&sample_settings).collect();
+    let writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    serde_json::to_writer_pretty(writer, &samples)?;
+    Ok(samples.len())
+}
+
+// Documents per independently seeded RNG. Part of the corpus definition: changing it changes
+// which documents a seed produces, while the number of threads never does.
+const CHUNK_SIZE: usize = 256;
+// Chunks generated per parallel round, per thread
+const CHUNKS_PER_THREAD: usize = 4;
+
+// Seeded document source; yields the corpus in order while generating it in parallel rounds,
+// so at most one round of documents is held in memory
+pub struct DocumentGenerator {
+    settings: GenerationSettings,
+    next_chunk: usize,
+    pending: std::vec::IntoIter<Value>,
+    remaining: usize,
+}
+
+impl DocumentGenerator {
+    pub fn new(settings: &GenerationSettings) -> Self {
+        Self { settings: *settings, next_chunk: 0, pending: Vec::new().into_iter(), remaining: settings.count }
+    }
+
+    // Generate the next round of chunks across the rayon pool, keeping chunk order
+    fn refill(&mut self) {
+        let total_chunks = self.settings.count.div_ceil(CHUNK_SIZE);
+        let round = (rayon::current_num_threads() * CHUNKS_PER_THREAD).min(total_chunks - self.next_chunk);
+        let settings = self.settings;
+        let chunks: Vec<Vec<Value>> = (self.next_chunk..self.next_chunk + round)
+            .into_par_iter()
+            .map(|chunk| generate_chunk(&settings, chunk))
             .collect();
+        self.next_chunk += round;
+        self.pending = chunks.into_iter().flatten().collect::<Vec<_>>().into_iter();
+    }
+}
+
+impl Iterator for DocumentGenerator {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        if self.remaining == 0 {
+            return None;
+        }
+        if self.pending.len() == 0 {
+            self.refill();
+        }
+        self.remaining -= 1;
+        self.pending.next()
+    }
 
-        // Generate Attributes (using serde_json::json! for structure)
-        // Ensure diverse types as requested
-        let attributes = json!({
-            // att0: number (integer)
-            "att0": rng.gen_range(0..1000),
-            // att1: string
-            "att1": fake::faker::company::en::Bs().fake::<String>(),
-            // att2: nested object (can be simple or complex)
-            "att2": {
-                "nested_key": fake::faker::internet::en::DomainSuffix().fake::<String>(),
-                "nested_bool": fake::faker::boolean::en::Boolean(50).fake::<bool>(), // 50% chance true/false
-            },
-            // att3: array of strings
-            "att3": fake::faker::lorem::en::Words(2..5).fake::<Vec<String>>(),
-            // Add potentially missing attribute sometimes for existence checks
-            format!("att_opt_{}", i % 5) : if rng.gen_bool(0.7) { // ~70% chance this optional key exists
-                Some(fake::faker::number::en::NumberWithFormat("###-##-####").fake::<String>())
-            } else {
-                None // This key won't be present in the JSON
-            }
-        });
-
-        let doc = json!({
-            "title": title,
-            "content": content,
-            "created_at": created_at.to_rfc3339(),
-            "tags": tags,
-            "attributes": attributes
-        });
-        println!("{:#?}",doc);
-        docs.push(doc.to_string());
-        pb.inc(1);
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
     }
-    pb.finish_with_message("Document generation complete");
+}
+
+// Every chunk has its own ChaCha stream of the seed, so chunks can be generated on any thread
+// in any order and still come out identical
+fn generate_chunk(settings: &GenerationSettings, chunk: usize) -> Vec<Value> {
+    // ChaCha is portable, so a seed produces the same stream on every platform
+    let mut rng = ChaCha8Rng::seed_from_u64(settings.seed);
+    rng.set_stream(chunk as u64);
+    let start = chunk * CHUNK_SIZE;
+    let end = (start + CHUNK_SIZE).min(settings.count);
+    (start..end)
+        .map(|i| generate_document(&mut rng, i, settings.reference_time))
+        .collect()
+}
+
+fn generate_document(rng: &mut ChaCha8Rng, i: usize, reference_time: DateTime<Utc>) -> Value {
+    // Drawn from the seeded RNG like every other field, so a seed always yields the same ids
+    let id = uuid::Builder::from_random_bytes(rng.random()).into_uuid();
+    let title = (CityName(JA_JP), 3..5).fake_with_rng::<Vec<String>, _>(rng).join(" ");
+    let content = (CityName(JA_JP), 5..50).fake_with_rng::<Vec<String>, _>(rng).join(" ");
+    let created_at = reference_time - chrono::Duration::days(rng.random_range(0..365));
+
+    // Generate Tags
+    let num_tags = rng.random_range(MIN_TAGS..=MAX_TAGS);
+    let tags: Vec<String> = (0..num_tags)
+        // Generate more realistic-looking tags (e.g., single words)
+        .map(|_| fake::faker::lorem::en::Word().fake_with_rng::<String, _>(rng).to_lowercase())
+        .collect();
 
-    docs
+    // Generate Attributes (using serde_json::json! for structure)
+    // Ensure diverse types as requested
+    let mut attributes = json!({
+        // att0: number (integer)
+        "att0": rng.random_range(0..1000),
+        // att1: string
+        "att1": fake::faker::company::en::Bs().fake_with_rng::<String, _>(rng),
+        // att2: nested object (can be simple or complex)
+        "att2": {
+            "nested_key": fake::faker::internet::en::DomainSuffix().fake_with_rng::<String, _>(rng),
+            "nested_bool": fake::faker::boolean::en::Boolean(50).fake_with_rng::<bool, _>(rng), // 50% chance true/false
+        },
+        // att3: array of strings
+        "att3": fake::faker::lorem::en::Words(2..5).fake_with_rng::<Vec<String>, _>(rng)
+    });
+    // Add potentially missing attribute sometimes for existence checks. Absent rather than null, as
+    // PostgreSQL `?` matches null values but Elasticsearch `exists` does not.
+    if rng.random_bool(0.7) { // ~70% chance this optional key exists
+        attributes[format!("att_opt_{}", i % 5)] =
+            fake::faker::number::en::NumberWithFormat("###-##-####").fake_with_rng::<String, _>(rng).into();
+    }
+
+    let doc = json!({
+        "id": id.to_string(),
+        "title": title,
+        "content": content,
+        "created_at": created_at.to_rfc3339(),
+        "tags": tags,
+        "attributes": attributes
+    });
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Spans several chunks and ends in a partial one
+    const COUNT: usize = 3 * CHUNK_SIZE + 17;
+
+    fn settings(count: usize, seed: u64) -> GenerationSettings {
+        GenerationSettings { count, seed, reference_time: "2025-01-01T00:00:00Z".parse().unwrap() }
+    }
+
+    fn corpus_bytes(settings: &GenerationSettings) -> Vec<u8> {
+        let docs: Vec<Value> = DocumentGenerator::new(settings).collect();
+        serde_json::to_vec(&docs).unwrap()
+    }
+
+    fn corpus_with_threads(settings: &GenerationSettings, threads: usize) -> Vec<u8> {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(|| corpus_bytes(settings))
+    }
+
+    #[test]
+    fn same_seed_yields_identical_bytes() {
+        assert_eq!(corpus_bytes(&settings(COUNT, 7)), corpus_bytes(&settings(COUNT, 7)));
+        assert_ne!(corpus_bytes(&settings(COUNT, 7)), corpus_bytes(&settings(COUNT, 8)));
+    }
+
+    #[test]
+    fn output_does_not_depend_on_thread_count() {
+        let single = corpus_with_threads(&settings(COUNT, 7), 1);
+        assert_eq!(single, corpus_with_threads(&settings(COUNT, 7), 7));
+    }
+
+    #[test]
+    fn smaller_count_is_an_exact_prefix() {
+        let full: Vec<Value> = DocumentGenerator::new(&settings(COUNT, 7)).collect();
+        for count in [0, 1, CHUNK_SIZE - 1, CHUNK_SIZE, CHUNK_SIZE + 1, COUNT - 1] {
+            let prefix: Vec<Value> = DocumentGenerator::new(&settings(count, 7)).collect();
+            assert_eq!(prefix, full[..count], "count {}", count);
+        }
+    }
+
+    #[test]
+    fn generator_yields_exactly_count_documents() {
+        let generator = DocumentGenerator::new(&settings(COUNT, 7));
+        assert_eq!(generator.size_hint(), (COUNT, Some(COUNT)));
+        assert_eq!(generator.count(), COUNT);
+    }
 }
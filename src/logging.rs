@@ -0,0 +1,30 @@
+// src/logging.rs
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing_subscriber::EnvFilter;
+
+use crate::cli::{LogArgs, LogFormat};
+
+// Progress bars are terminal I/O too; quiet runs and JSON logs switch them off
+static PROGRESS_BARS: AtomicBool = AtomicBool::new(true);
+
+// Status messages go to stderr through tracing; result tables stay on stdout
+pub fn init(args: &LogArgs) {
+    // RUST_LOG wins so individual modules can still be turned up
+    let level = if args.quiet { "warn" } else { args.log_level.as_str() };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_target(false);
+    match args.log_format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+
+    PROGRESS_BARS.store(!args.quiet && args.log_format == LogFormat::Text, Ordering::Relaxed);
+}
+
+pub fn progress_bars_enabled() -> bool {
+    PROGRESS_BARS.load(Ordering::Relaxed)
+}
@@ -1,25 +1,37 @@
 // src/main.rs
-use std::time::{Duration, Instant};
-use std::env;
+use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::io::{BufWriter, Write};
+use clap::ValueEnum;
 use dotenv::dotenv;
-use elasticsearch::{
-    Elasticsearch, BulkOperation, Error as EsError, http::transport::Transport, SearchParts,
-    BulkParts, indices::{IndicesExistsParts, IndicesCreateParts, IndicesRefreshParts},
-};
-use serde_json::{Value, json}; // Keep Value, add json macro usage
-use serde::{Deserialize, Serialize};
+use elasticsearch::{Elasticsearch, Error as EsError};
 use thiserror::Error;
-use tokio_postgres::{Client, NoTls, Error as PgError};
-use tokio_postgres::types::{Type, ToSql}; // Add ToSql
-use futures_util::pin_mut;
-use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tokio_postgres::{Client, Error as PgError};
+use tracing::info;
 
-// Declare the module
+// Declare the modules
+mod cli;
 mod generate_data;
-
-const BATCH_SIZE: usize = 1000; // Increase batch size for COPY/Bulk
-const ES_INDEX_NAME: &str = "documents_jsonb"; // New index name
-const PG_TABLE_NAME: &str = "documents_jsonb"; // New table name
+mod stats;
+mod concurrent;
+mod workload;
+mod report;
+mod compare;
+mod postgres;
+mod elastic;
+mod stream;
+mod logging;
+mod verify;
+mod writes;
+
+use cli::{AllArgs, BenchArgs, BenchMode, Cli, Command, DataArgs, GenerateArgs, PgIndexTiming, ReportArgs, SampleArgs, SchemaArgs, TargetArgs};
+use report::{Backend, BenchmarkReport, IngestReport, QueryReport, StorageReport};
+use postgres::PgIndexProfile;
+use stream::DocumentSource;
+use concurrent::LoadSettings;
+use elastic::BulkSettings;
+use workload::Workload;
 
 #[derive(Error, Debug)]
 enum BenchmarkError {
@@ -31,491 +43,453 @@ enum BenchmarkError {
     Json(#[from] serde_json::Error),
     #[error("IO Error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("CSV Error: {0}")]
+    Csv(#[from] csv::Error),
     #[error("Environment variable not set: {0}")]
     EnvVar(String),
     #[error("URL Parse Error: {0}")]
     UrlParse(#[from] url::ParseError),
     #[error("Elasticsearch Bulk Operation Error: {0}")]
     EsBulkError(String),
-    #[error("Data Conversion Error: {0}")]
-    Conversion(String),
+    #[error("Elasticsearch Query Error: {0}")]
+    EsQuery(String),
+    #[error("Workload Error: {0}")]
+    Workload(String),
+    #[error("Ingest Error: {0}")]
+    Ingest(String),
+    #[error("Schema Error: {0}")]
+    Schema(String),
 }
 
-// Updated struct to match the new JSON structure
-// We'll primarily work with serde_json::Value for flexibility,
-// but having a struct can be useful for validation or specific cases.
-#[derive(Serialize, Deserialize, Debug, Clone)] // Add Clone
-struct Document {
-    title: String,
-    content: String,
-    created_at: chrono::DateTime<chrono::Utc>,
-    tags: Vec<String>,
-    attributes: Value, // Use Value for flexible attributes object
+// Clients for the backends selected on the command line
+struct Connections {
+    pg: Option<Client>,
+    es: Option<Elasticsearch>,
 }
 
+impl Connections {
+    async fn open(target: &TargetArgs) -> Result<Self, BenchmarkError> {
+        info!("Connecting to databases...");
+        let pg = if target.postgres() { Some(postgres::connect_postgres().await?) } else { None };
+        let es = if target.elasticsearch() { Some(elastic::connect_elasticsearch()?) } else { None };
+        info!("Connections established.");
+        Ok(Self { pg, es })
+    }
+
+    async fn record_versions(&self, report: &mut BenchmarkReport) {
+        if let Some(pg) = &self.pg {
+            report.environment.postgres_version = postgres::postgres_version(pg).await;
+        }
+        if let Some(es) = &self.es {
+            report.environment.elasticsearch_version = elastic::elasticsearch_version(es).await;
+        }
+    }
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
     dotenv().ok();
-
-    println!("Starting benchmark with JSONB focus...");
-
-    // --- Connections (remain the same) ---
-    println!("Connecting to databases...");
-    let pg_client = connect_postgres().await?;
-    let transport = Transport::single_node(
-        &env::var("ELASTICSEARCH_URL")
-            .unwrap_or_else(|_| "http://localhost:9200".to_string())
-    )?;
-    let es_client = Elasticsearch::new(transport);
-    println!("Connections established.");
-
-    // --- Setup (modified for JSONB and new ES mapping) ---
-    println!("Setting up database schemas...");
-    setup_postgres(&pg_client).await?;
-    setup_elasticsearch(&es_client).await?;
-    println!("Schemas ready.");
-
-    // --- Data Generation (uses updated generate_data.rs) ---
-    // Read data count from environment variable or use default
-    let data_count_str = env::var("DATA_COUNT").unwrap_or_else(|_| "1000".to_string()); // Default to 1K
-    let data_count: usize = data_count_str.parse().unwrap_or_else(|e| {
-        eprintln!("WARN: Invalid DATA_COUNT '{}', using default 1,000. Error: {}", data_count_str, e);
-        1_000
-    });
-
-    println!("Generating {} documents...", data_count);
-    let start_gen = Instant::now();
-    let docs_json_strings = generate_data::generate_documents(data_count).await;
-    println!("Data generation took: {:?}", start_gen.elapsed());
-
-    // --- Parse JSON strings into Value for insertion ---
-    // We need Value for both PG JSONB COPY and ES Bulk
-    println!("Parsing JSON strings...");
-    let start_parse = Instant::now();
-    let docs_value: Vec<Value> = docs_json_strings
-        .iter()
-        .map(|s| serde_json::from_str(s))
-        .collect::<Result<Vec<_>, _>>()?;
-    println!("JSON parsing took: {:?}", start_parse.elapsed());
-
-    // --- Insertion (modified for JSONB COPY and ES Bulk) ---
-    println!("Inserting data into PostgreSQL (JSONB)...");
-    let start_pg_insert = Instant::now();
-    insert_postgres(&pg_client, &docs_value).await?;
-    println!("PostgreSQL JSONB insertion took: {:?}", start_pg_insert.elapsed());
-
-    println!("Inserting data into Elasticsearch...");
-    let start_es_insert = Instant::now();
-    // Pass Value directly to ES insert function
-    insert_elasticsearch_value(&es_client, &docs_value).await?;
-    println!("Elasticsearch insertion took: {:?}", start_es_insert.elapsed());
-
-    // --- Benchmarks (modified queries) ---
-    // Define queries suitable for JSONB and ES structure
-    let pg_queries = vec![
-        // Tag containment ('@>') - Does tags array contain ["rust"]?
-        ("tags @> 'rust'", json!(["rust"]).to_string()),
-        // Attribute key existence ('?') - Does attributes object have key 'att1'?
-        ("attr ? 'att1'", "att1".to_string()),
-        // Nested attribute value ('->>') - Is attributes.att2.nested_key == 'com'?
-        ("attr nested = 'com'", "com".to_string()), // We'll use ->> inside the query
-        // Attribute value comparison ('>') - Is attributes.att0 > 500?
-        ("attr att0 > 500", json!(500).to_string()),
-        // Optional attribute existence ('?')
-        ("attr ? 'att_opt_1'", "att_opt_1".to_string()),
-        // JSONPath query on title
-        ("jsonpath: title like_regex", r#"$.title like_regex ".*鈴木.*""#.to_string()),
-        // Non-existent tag
-        ("tags @> 'nonexistent'", json!(["nonexistent"]).to_string()),
-    ];
-
-    let es_queries = vec![
-        // Match a specific tag (term query on keyword field)
-        ("tags: rust", json!({"term": {"tags": "rust"}})),
-        // Check for attribute existence (exists query)
-        ("exists: attributes.att1", json!({"exists": {"field": "attributes.att1"}})),
-        // Match nested attribute value (term query on keyword/text)
-        // Assuming default mapping makes nested_key text/keyword
-        ("attributes.att2.nested_key: com", json!({"term": {"attributes.att2.nested_key": "com"}})),
-         // Range query on numeric attribute
-        ("attributes.att0 > 500", json!({"range": {"attributes.att0": {"gt": 500}}})),
-        // Check for optional attribute existence
-        ("exists: attributes.att_opt_1", json!({"exists": {"field": "attributes.att_opt_1"}})),
-        // Regex query on title (equivalent to the jsonpath like_regex)
-        ("title regex '鈴木'", json!({
-            "match": {
-                "title": "chester 栞奈"
+    let cli = Cli::parse_args();
+    logging::init(&cli.log);
+
+    match cli.command {
+        None => run_all(&cli.all).await?,
+        Some(Command::All(args)) => run_all(&args).await?,
+        Some(Command::Setup(args)) => {
+            let conns = Connections::open(&args.target).await?;
+            setup(&conns, &args.target, &args.schema).await?;
+        }
+        Some(Command::Generate(args)) => generate(&args).await?,
+        Some(Command::Load(args)) => {
+            let conns = Connections::open(&args.target).await?;
+            let mut report = BenchmarkReport::new("none", args.data.generation.count);
+            conns.record_versions(&mut report).await;
+            report.data_count = load(&conns, &args.target, &args.data, &mut report).await?;
+            export_report(&report, &args.report)?;
+        }
+        Some(Command::Bench(args)) => {
+            let workload = load_workload(args.bench.workload.as_deref())?;
+            let conns = Connections::open(&args.target).await?;
+            let data_count = loaded_document_count(&conns, &args.target).await?;
+            let mut report = BenchmarkReport::new(&workload_source(args.bench.workload.as_deref()), data_count);
+            conns.record_versions(&mut report).await;
+            bench(&conns, &args.target, &args.bench, &workload, &mut report).await?;
+            export_report(&report, &args.report)?;
+        }
+        Some(Command::Drop(target)) => {
+            let conns = Connections::open(&target).await?;
+            if let Some(pg) = &conns.pg {
+                postgres::drop_postgres(pg, &target.table).await?;
+            }
+            if let Some(es) = &conns.es {
+                elastic::drop_elasticsearch(es, &target.index).await?;
             }
-        })),
-        // Non-existent tag
-        ("tags: nonexistent", json!({"term": {"tags": "nonexistent"}})),
-    ];
+        }
+        Some(Command::Verify(args)) => {
+            let workload = load_workload(args.workload.as_deref())?;
+            let conns = Connections::open(&args.target).await?;
+            let (Some(pg), Some(es)) = (&conns.pg, &conns.es) else {
+                return Err(BenchmarkError::Workload("verify needs both backends".to_string()).into());
+            };
+            let data_count = loaded_document_count(&conns, &args.target).await?;
+            let mut report = BenchmarkReport::new(&workload_source(args.workload.as_deref()), data_count);
+            conns.record_versions(&mut report).await;
+            report.verification = verify::verify_workload(pg, &args.target.table, es, &args.target.index, &workload, args.draws as usize).await?;
+            export_report(&report, &args.report)?;
+            if report.verification.iter().any(|v| !v.matches()) {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Some(Command::Compare(args)) => {
+            let regressions = compare::compare_runs(&args)?;
+            if regressions > 0 {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+// Setup, load and bench in one go
+async fn run_all(args: &AllArgs) -> Result<(), BenchmarkError> {
+    info!("Starting benchmark with JSONB focus...");
 
+    // Load the workload first so a broken file fails before any data is loaded
+    let workload = load_workload(args.bench.workload.as_deref())?;
+    let mut report = BenchmarkReport::new(&workload_source(args.bench.workload.as_deref()), args.data.generation.count);
 
-    println!("\nRunning PostgreSQL JSONB benchmarks...");
-    benchmark_postgres(&pg_client, &pg_queries).await?;
+    let conns = Connections::open(&args.target).await?;
+    conns.record_versions(&mut report).await;
 
-    println!("\nRunning Elasticsearch benchmarks...");
-    benchmark_elasticsearch(&es_client, &es_queries).await?;
+    setup(&conns, &args.target, &args.schema).await?;
+    report.data_count = load(&conns, &args.target, &args.data, &mut report).await?;
+    bench(&conns, &args.target, &args.bench, &workload, &mut report).await?;
+    export_report(&report, &args.report)?;
 
-    println!("\nBenchmark finished.");
+    info!("Benchmark finished.");
     Ok(())
 }
 
-// --- Connection Functions (remain the same) ---
-async fn connect_postgres() -> Result<Client, BenchmarkError> {
-    let db_url = env::var("DATABASE_URL")
-        .map_err(|_| BenchmarkError::EnvVar("DATABASE_URL".to_string()))?;
-    let (client, connection) = tokio_postgres::connect(&db_url, NoTls).await?;
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("PostgreSQL connection error: {}", e);
-        }
-    });
-    Ok(client)
+fn load_workload(path: Option<&Path>) -> Result<Workload, BenchmarkError> {
+    let workload = match path {
+        Some(path) => Workload::from_file(path)?,
+        None => Workload::builtin()?,
+    };
+    info!("Loaded workload with {} queries.", workload.queries.len());
+    Ok(workload)
 }
 
-// --- Setup Functions (Updated for JSONB and new ES Mapping) ---
+fn workload_source(path: Option<&Path>) -> String {
+    path.map_or_else(|| "built-in".to_string(), |path| path.display().to_string())
+}
 
-async fn setup_postgres(client: &Client) -> Result<(), BenchmarkError> {
-    // Create table with a single JSONB column
-    // Add a GIN index for efficient JSONB operations
-    client.batch_execute(&format!(
-        r#"
-        CREATE TABLE IF NOT EXISTS {PG_TABLE_NAME} (
-            id SERIAL PRIMARY KEY, -- Keep ID for potential reference
-            data JSONB NOT NULL
-        );
-        -- Create a GIN index on the JSONB column. This is crucial for performance.
-        CREATE INDEX IF NOT EXISTS documents_data_gin_idx ON {PG_TABLE_NAME} USING GIN(data);
-        -- CREATE INDEX IF NOT EXISTS documents_data_gin_json_idx ON {PG_TABLE_NAME} USING GIN (data jsonb_path_ops);
-        -- CREATE INDEX IF NOT EXISTS documents_data_gin_jsonb_idx ON {PG_TABLE_NAME} USING GIN (data jsonb_ops);
-
-        -- Optional: Index specific paths if needed for very specific query patterns
-        -- CREATE INDEX IF NOT EXISTS documents_tags_gin_idx ON {PG_TABLE_NAME} USING GIN ((data -> 'tags'));
-        -- CREATE INDEX IF NOT EXISTS documents_attr_gin_idx ON {PG_TABLE_NAME} USING GIN ((data -> 'attributes'));
-
-        -- Optional: Clear table for a fresh benchmark run
-        -- TRUNCATE TABLE {PG_TABLE_NAME} RESTART IDENTITY;
-        "#, PG_TABLE_NAME=PG_TABLE_NAME)
-    ).await?;
-    println!("PostgreSQL table '{}' with JSONB column and GIN index checked/created.", PG_TABLE_NAME);
+// --- Pipeline Steps ---
+
+async fn setup(conns: &Connections, target: &TargetArgs, schema: &SchemaArgs) -> Result<(), BenchmarkError> {
+    info!("Setting up database schemas...");
+    if let Some(pg) = &conns.pg {
+        postgres::setup_postgres(pg, &target.table, target.pg_index_profile, schema.existing).await?;
+    }
+    if let Some(es) = &conns.es {
+        elastic::setup_elasticsearch(es, &target.index, schema.existing).await?;
+    }
+    info!("Schemas ready.");
     Ok(())
 }
 
-async fn setup_elasticsearch(client: &Elasticsearch) -> Result<(), BenchmarkError> {
-    let index_exists = client
-        .indices()
-        .exists(IndicesExistsParts::Index(&[ES_INDEX_NAME]))
-        .send()
-        .await?
-        .status_code()
-        .is_success();
-
-    if !index_exists {
-        println!("Creating Elasticsearch index '{}' with new mapping...", ES_INDEX_NAME);
-        let create_response = client
-            .indices()
-            .create(IndicesCreateParts::Index(ES_INDEX_NAME))
-            .body(json!({
-                "mappings": {
-                    "properties": {
-                        "title": { "type": "text" },
-                        "content": { "type": "text" },
-                        "created_at": { "type": "date" },
-                        // Index tags as keyword for exact matching, filtering, aggregations
-                        "tags": { "type": "keyword" },
-                        // Index attributes as an object. Dynamic mapping will handle sub-fields.
-                        // For production, you might explicitly map known attributes
-                        // (e.g., "att0": {"type": "integer"}) for better control.
-                        "attributes": {
-                            "type": "object",
-                            // "enabled": true // default is true
-                            "properties": {
-                                "att0": { "type": "integer" }, // Explicitly map known numeric field
-                                "att1": { "type": "text", "fields": { "keyword": { "type": "keyword", "ignore_above": 256 }}}, // Text + keyword
-                                "att2": { "type": "object", "enabled": true }, // Allow dynamic mapping within att2
-                                "att3": { "type": "keyword" } // Array of strings often best as keyword
-                                // Optional attributes (att_opt_*) will be dynamically mapped
-                            }
-                        }
-                    }
-                }
-            }))
-            .send()
-            .await?;
-
-        if !create_response.status_code().is_success() {
-            let response_body = create_response.text().await?;
-            eprintln!("Failed to create index '{}': {}", ES_INDEX_NAME, response_body);
-            return Err(BenchmarkError::EsBulkError(format!(
-                "Failed to create index '{}'", ES_INDEX_NAME
-            )));
-        }
-         println!("Elasticsearch index '{}' created.", ES_INDEX_NAME);
-    } else {
-        println!("Elasticsearch index '{}' already exists.", ES_INDEX_NAME);
-        // Optional: Delete index for a fresh run
-        // println!("Deleting existing Elasticsearch index '{}'...", ES_INDEX_NAME);
-        // client.indices().delete(IndicesDeleteParts::Index(&[ES_INDEX_NAME])).send().await?;
-        // setup_elasticsearch(client).await?; // Recurse to create it
+async fn generate(args: &GenerateArgs) -> Result<(), BenchmarkError> {
+    let settings = args.generation.settings();
+    dump_samples(&settings, &args.sample)?;
+    info!("Generating {} documents (seed {})...", settings.count, settings.seed);
+    let start_gen = Instant::now();
+    let pb = stream::progress_bar(Some(settings.count as u64));
+
+    // Write each document as it is generated so the file size is not bounded by memory
+    let mut writer = BufWriter::new(std::fs::File::create(&args.output)?);
+    for doc in generate_data::DocumentGenerator::new(&settings) {
+        serde_json::to_writer(&mut writer, &doc)?;
+        writer.write_all(b"\n")?;
+        pb.inc(1);
     }
+    writer.flush()?;
+    pb.finish_with_message("Document generation complete");
+    info!("Data generation took: {:?}", start_gen.elapsed());
+    info!("Wrote {} documents to {} (seed {})", settings.count, args.output.display(), settings.seed);
     Ok(())
 }
 
+// Insert documents into the selected backends; returns how many documents were loaded
+async fn load(conns: &Connections, target: &TargetArgs, data: &DataArgs, report: &mut BenchmarkReport) -> Result<usize, BenchmarkError> {
+    // Documents are streamed straight into each backend, so generation (or reading the input file)
+    // overlaps with insertion and is included in the insert timings
+    let source = match &data.input {
+        Some(path) => {
+            info!("Streaming documents from {}...", path.display());
+            DocumentSource::File(path.clone())
+        }
+        None => {
+            let settings = data.generation.settings();
+            report.seed = Some(settings.seed);
+            dump_samples(&settings, &data.sample)?;
+            info!("Streaming {} generated documents (seed {})...", settings.count, settings.seed);
+            DocumentSource::Generated(settings)
+        }
+    };
+    let mut loaded = 0;
 
-// --- Insertion Functions (Updated for JSONB COPY and ES Value) ---
+    // --- Insertion (modified for JSONB COPY and ES Bulk) ---
+    if let Some(pg) = &conns.pg {
+        let deferred_indexes = data.pg_index_timing == PgIndexTiming::After;
+        if deferred_indexes {
+            postgres::drop_key_index(pg, &target.table).await?;
+            postgres::drop_indexes(pg, &target.table).await?;
+        }
+        // Every strategy loads the full corpus; the table keeps the result of the last one
+        for (i, strategy) in data.pg_insert_strategy.iter().enumerate() {
+            if i > 0 {
+                postgres::truncate_postgres(pg, &target.table).await?;
+            }
+            let label = strategy.label();
+            info!("Inserting data into PostgreSQL (JSONB) using {}...", label);
+            let start_pg_insert = Instant::now();
+            let docs = source.stream(data.stream_buffer);
+            let shards = postgres::insert_postgres_sharded(&target.table, *strategy, docs, source.expected_len(), data.pg_shards as usize, data).await?;
+            let pg_elapsed = start_pg_insert.elapsed();
+            for shard in &shards {
+                let ingest = IngestReport::new(Backend::Postgres, &format!("{} shard {}", label, shard.shard), shard.documents, shard.duration);
+                info!("PostgreSQL {} shard {}: {} documents in {:?} ({:.0} docs/s)", label, shard.shard, shard.documents, shard.duration, ingest.docs_per_sec);
+                report.ingest.push(ingest);
+            }
+            loaded = shards.iter().map(|s| s.documents).sum();
+            let total = IngestReport::new(Backend::Postgres, &format!("{} total", label), loaded, pg_elapsed);
+            info!("PostgreSQL JSONB insertion ({}) of {} documents took: {:?} ({:.0} docs/s)", label, loaded, pg_elapsed, total.docs_per_sec);
+            report.ingest.push(total);
+            report.record_phase(&format!("postgres_insert_{}", label), pg_elapsed);
+        }
 
-async fn insert_postgres(client: &Client, docs: &[Value]) -> Result<(), BenchmarkError> {
-    // Use COPY BINARY for efficient bulk insertion of JSONB
-    let copy_stmt = format!(
-        // Copy into the 'data' column
-        "COPY {PG_TABLE_NAME} (data) FROM STDIN (FORMAT BINARY)",
-        PG_TABLE_NAME = PG_TABLE_NAME
-    );
+        if deferred_indexes {
+            info!("Building PostgreSQL indexes on the loaded table...");
+            let start_index = Instant::now();
+            report.indexes.push(postgres::create_key_index(pg, &target.table).await?);
+            report.indexes.extend(postgres::create_indexes(pg, &target.table, target.pg_index_profile).await?);
+            info!("PostgreSQL index build took: {:?}", start_index.elapsed());
+            report.record_phase("postgres_index_build", start_index.elapsed());
+        } else {
+            report.indexes.extend(postgres::index_sizes(pg, &target.table, target.pg_index_profile).await?);
+        }
+        report.storage.push(postgres::storage_report(pg, &target.table).await?);
+    }
 
-    let sink = client.copy_in(&copy_stmt).await?;
+    if let Some(es) = &conns.es {
+        info!("Inserting data into Elasticsearch...");
+        let previous_settings = if data.es_ingest_settings { Some(elastic::apply_ingest_settings(es, &target.index).await?) } else { None };
+        let start_es_insert = Instant::now();
+        let docs = source.stream(data.stream_buffer);
+        let bulk = elastic::insert_elasticsearch_value(es, &target.index, &BulkSettings::from(data), docs, source.expected_len()).await;
+        let es_elapsed = start_es_insert.elapsed();
+
+        if let Some(previous) = &previous_settings {
+            // Merge while there are no replicas yet, so the restored replicas copy the merged segments only
+            let merged = if bulk.is_ok() {
+                let start_merge = Instant::now();
+                let merged = elastic::force_merge(es, &target.index).await;
+                info!("Elasticsearch force-merge took: {:?}", start_merge.elapsed());
+                report.record_phase("elasticsearch_force_merge", start_merge.elapsed());
+                merged
+            } else {
+                Ok(())
+            };
+            // Put the index settings back even when the load failed; timed until the replicas are allocated again
+            let start_restore = Instant::now();
+            elastic::restore_index_settings(es, &target.index, previous).await?;
+            info!("Elasticsearch settings restore took: {:?}", start_restore.elapsed());
+            report.record_phase("elasticsearch_restore_settings", start_restore.elapsed());
+            merged?;
+        }
+        let bulk = bulk?;
+        loaded = bulk.documents;
 
-    // The type for the 'data' column is JSONB
-    let types = &[Type::JSONB];
-    let writer = BinaryCopyInWriter::new(sink, types);
-    pin_mut!(writer);
+        let mut ingest = IngestReport::new(Backend::Elasticsearch, "bulk total", bulk.documents, es_elapsed - bulk.refresh);
+        info!(
+            "Elasticsearch insertion of {} documents in {} bulk requests took: {:?} ({:.0} docs/s, {} failed, {} retries)",
+            bulk.documents, bulk.batches, es_elapsed, ingest.docs_per_sec, bulk.failed, bulk.retries
+        );
+        stats::print_stats_header();
+        stats::print_stats_row("bulk request", bulk.batches, &bulk.request_latency);
+        ingest.request_latency = Some(bulk.request_latency);
+        ingest.failed_documents = bulk.failed;
+        ingest.retries = bulk.retries;
+        report.ingest.push(ingest);
+        report.record_phase("elasticsearch_insert", es_elapsed);
+        report.record_phase("elasticsearch_refresh", bulk.refresh);
+        report.storage.push(elastic::storage_report(es, &target.index).await?);
+    }
 
-    println!("Starting PostgreSQL COPY operation for {} documents...", docs.len());
-    let pb = indicatif::ProgressBar::new(docs.len() as u64);
-     pb.set_style(indicatif::ProgressStyle::default_bar()
-        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
-        .unwrap()
-        .progress_chars("#>-"));
+    print_storage(&report.storage);
 
+    Ok(loaded)
+}
 
-    // Iterate through the serde_json::Value objects and write them
-    // serde_json::Value implements ToSql for JSONB
-    for doc_value in docs {
-        // write expects a slice of references implementing ToSql
-        writer.as_mut().write(&[doc_value]).await?;
-        pb.inc(1);
+fn dump_samples(settings: &generate_data::GenerationSettings, args: &SampleArgs) -> Result<(), BenchmarkError> {
+    if let Some(limit) = args.sample_dump {
+        let written = generate_data::dump_samples(settings, limit, &args.sample_file)?;
+        info!("Wrote {} sample documents to {}", written, args.sample_file.display());
     }
-
-    // Finish the COPY operation
-    writer.finish().await?;
-    pb.finish_with_message("PostgreSQL COPY complete");
-
     Ok(())
 }
 
-// Keep the original insert_elasticsearch but rename it slightly
-// This version works if you have Vec<Document>
-// async fn insert_elasticsearch_struct(client: &Elasticsearch, docs: &[Document]) -> Result<(), BenchmarkError> { ... }
-
-// New version accepting Vec<Value> directly
-async fn insert_elasticsearch_value(client: &Elasticsearch, docs: &[Value]) -> Result<(), BenchmarkError> {
-    let chunks = docs.chunks(BATCH_SIZE);
-
-    println!("Inserting {} documents into Elasticsearch in batches of {}...", docs.len(), BATCH_SIZE);
-    let pb = indicatif::ProgressBar::new(docs.len() as u64);
-    pb.set_style(indicatif::ProgressStyle::default_bar()
-        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}")
-        .unwrap()
-        .progress_chars("#>-"));
-
-    for chunk in chunks {
-        let mut operations: Vec<BulkOperation<Value>> = Vec::with_capacity(chunk.len());
-
-        for doc_value in chunk {
-            // Since we already have Value, just clone it for the operation
-            // Use BulkOperation::index(doc_value.clone()).into()
-            let op = BulkOperation::index(doc_value.clone()).into();
-            operations.push(op);
-            pb.inc(1);
+async fn bench(conns: &Connections, target: &TargetArgs, args: &BenchArgs, workload: &Workload, report: &mut BenchmarkReport) -> Result<(), BenchmarkError> {
+    if let Some(pg) = &conns.pg {
+        match &args.pg_index_matrix {
+            Some(profiles) => bench_pg_index_matrix(pg, target, args, workload, profiles, report).await?,
+            None => {
+                report.queries.extend(bench_postgres(pg, target, args, workload).await?);
+                if args.explain {
+                    info!("Explaining PostgreSQL queries...");
+                    report.plans.extend(postgres::explain_postgres(pg, &target.table, workload, args.count_totals).await?);
+                }
+            }
         }
+    }
+    if let Some(es) = &conns.es {
+        report.queries.extend(bench_elasticsearch(es, target, args, workload).await?);
+    }
+    Ok(())
+}
 
-        if operations.is_empty() {
-            continue;
+async fn bench_postgres(pg: &Client, target: &TargetArgs, args: &BenchArgs, workload: &Workload) -> Result<Vec<QueryReport>, BenchmarkError> {
+    match args.mode {
+        // Runs the query mix from several workers at once instead of one query at a time
+        BenchMode::Concurrent => {
+            let load_settings = LoadSettings::from(args);
+            info!("Running PostgreSQL JSONB load test ({} workers, {:?})...", load_settings.workers, load_settings.limit);
+            concurrent::load_test_postgres(&target.table, workload, &load_settings).await
         }
-
-        let response = client
-            .bulk(BulkParts::Index(ES_INDEX_NAME))
-            .body(operations)
-            .send()
-            .await?;
-
-        let status = response.status_code();
-
-        if !status.is_success() {
-            pb.finish_with_message(format!("Error during bulk insert (HTTP Status: {})!", status));
-            let response_body_text = response.text().await?;
-            eprintln!("Elasticsearch bulk insert failed with status {}: {}", status, response_body_text);
-            return Err(BenchmarkError::EsBulkError(format!(
-                "Bulk insert failed with status {} - Body: {}", status, response_body_text
-            )));
+        BenchMode::Sequential => {
+            info!("Running PostgreSQL JSONB benchmarks...");
+            postgres::benchmark_postgres(pg, &target.table, workload, args).await
         }
-
-        let response_body = response.json::<Value>().await?;
-
-        if let Some(true) = response_body.get("errors").and_then(|v| v.as_bool()) {
-             pb.set_message(format!("Batch completed with item errors."));
-             eprintln!("WARNING: Elasticsearch bulk operation reported errors for some items. Check response details.");
-             // Consider logging response_body here for debugging errors
-             // eprintln!("Bulk response with errors: {:?}", response_body);
-        } else {
-             pb.set_message(format!("Batch successful."));
+        BenchMode::Writes => {
+            info!("Running PostgreSQL JSONB write benchmarks...");
+            writes::benchmark_writes(&writes::Writer::postgres(&target.table).await?, args).await
+        }
+        BenchMode::Mixed => {
+            let load_settings = LoadSettings::from(args);
+            info!("Running PostgreSQL JSONB mixed load test ({} readers, {} writers, {:?})...", load_settings.workers, args.writers, load_settings.limit);
+            let mut writers = Vec::new();
+            for _ in 0..args.writers {
+                writers.push(writes::Writer::postgres(&target.table).await?);
+            }
+            writes::with_background_writes(writers, concurrent::load_test_postgres(&target.table, workload, &load_settings)).await
         }
     }
-    pb.finish_with_message("Elasticsearch insertion complete");
-
-    // Force a refresh
-    println!("Refreshing Elasticsearch index...");
-    let refresh_start = Instant::now();
-    client.indices().refresh(IndicesRefreshParts::Index(&[ES_INDEX_NAME])).send().await?;
-    println!("Elasticsearch refresh took: {:?}", refresh_start.elapsed());
-
-    Ok(())
 }
 
+async fn bench_elasticsearch(es: &Elasticsearch, target: &TargetArgs, args: &BenchArgs, workload: &Workload) -> Result<Vec<QueryReport>, BenchmarkError> {
+    match args.mode {
+        BenchMode::Concurrent => {
+            let load_settings = LoadSettings::from(args);
+            info!("Running Elasticsearch load test ({} workers, {:?})...", load_settings.workers, load_settings.limit);
+            concurrent::load_test_elasticsearch(es, &target.index, workload, &load_settings).await
+        }
+        BenchMode::Sequential => {
+            info!("Running Elasticsearch benchmarks...");
+            elastic::benchmark_elasticsearch(es, &target.index, workload, args).await
+        }
+        BenchMode::Writes => {
+            info!("Running Elasticsearch write benchmarks...");
+            writes::benchmark_writes(&writes::Writer::elasticsearch(es, &target.index), args).await
+        }
+        BenchMode::Mixed => {
+            let load_settings = LoadSettings::from(args);
+            info!("Running Elasticsearch mixed load test ({} readers, {} writers, {:?})...", load_settings.workers, args.writers, load_settings.limit);
+            let writers = (0..args.writers).map(|_| writes::Writer::elasticsearch(es, &target.index)).collect();
+            writes::with_background_writes(writers, concurrent::load_test_elasticsearch(es, &target.index, workload, &load_settings)).await
+        }
+    }
+}
 
-// --- Benchmark Functions (Updated for JSONB and new ES Queries) ---
-
-async fn benchmark_postgres(client: &Client, queries: &[(&str, String)]) -> Result<(), BenchmarkError> {
-    println!("{:<25} | {:<10} | {:<15}", "Query Type", "Count", "Latency (ms)");
-    println!("{:-<60}", "");
-
-    let mut total_latency = Duration::ZERO;
-    let mut total_rows_found = 0;
-    let query_count = queries.len();
-
-    // Prepare different statements for different JSONB operations
-    // Note: Parameter types might need adjustment based on the operator
-    let prep_tag_contains = client.prepare(&format!(
-        "SELECT data ->> 'title' FROM {PG_TABLE_NAME} WHERE data -> 'tags' @> $1::jsonb LIMIT 10", PG_TABLE_NAME=PG_TABLE_NAME
-    )).await?;
-    let prep_attr_exists = client.prepare(&format!(
-        "SELECT data ->> 'title' FROM {PG_TABLE_NAME} WHERE data -> 'attributes' ? $1 LIMIT 10", PG_TABLE_NAME=PG_TABLE_NAME
-    )).await?;
-    let prep_nested_attr_eq = client.prepare(&format!(
-        "SELECT data ->> 'title' FROM {PG_TABLE_NAME} WHERE data -> 'attributes' -> 'att2' ->> 'nested_key' = $1 LIMIT 10", PG_TABLE_NAME=PG_TABLE_NAME
-    )).await?;
-     let prep_attr_compare_num = client.prepare(&format!(
-        // Ensure casting for comparison. Use numeric for broader compatibility.
-        "SELECT data ->> 'title' FROM {PG_TABLE_NAME} WHERE (data -> 'attributes' ->> 'att0')::numeric > 500::numeric LIMIT 10", PG_TABLE_NAME=PG_TABLE_NAME
-    )).await?;
-    let prep_jsonpath_field2_match = client.prepare(&format!(
-        "SELECT data ->> 'title' FROM {PG_TABLE_NAME} WHERE data @@ '$.title like_regex \".*chester.*\"' AND data @@ '$.title like_regex \".*栞奈.*\"' LIMIT 10", PG_TABLE_NAME=PG_TABLE_NAME
-    )).await?;
-
-
-    for (query_desc, query_param_str) in queries {
-        let start = Instant::now();
-        let rows = match *query_desc {
-            q if q.starts_with("tags @>") => {
-                // Parameter needs to be a valid JSON string representing the array/value
-                let param_jsonb: Value = serde_json::from_str(&query_param_str)
-                    .map_err(|e| BenchmarkError::Conversion(format!("Invalid JSON for tag query: {} - {}", query_param_str, e)))?;
-                client.query(&prep_tag_contains, &[&param_jsonb]).await?
-            },
-            q if q.starts_with("attr ?") => {
-                // Parameter is the key name (string)
-                client.query(&prep_attr_exists, &[&query_param_str]).await?
-            },
-            q if q.starts_with("attr nested =") => {
-                 // Parameter is the value to compare against (string)
-                client.query(&prep_nested_attr_eq, &[&query_param_str]).await?
-            },
-             q if q.starts_with("attr att0 >") => {
-                // Parameter needs to be parsed as a number
-                let param_num: f64 = query_param_str.parse()
-                     .map_err(|e| BenchmarkError::Conversion(format!("Invalid number for comparison: {} - {}", query_param_str, e)))?;
-                // Pass as f64, which ToSql handles for numeric
-                client.query(&prep_attr_compare_num, &[]).await?
-            },
-            q if q.starts_with("jsonpath: title like_regex") => {
-                // Parameter is the jsonpath expression string
-                client.query(&prep_jsonpath_field2_match, &[]).await?
-            }
-            _ => {
-                println!("WARN: Unsupported PG query description: {}", query_desc);
-                vec![] // Return empty vec if query type not recognized
+// Runs the PostgreSQL suite once per index profile on the same data, rebuilding the indexes in between
+async fn bench_pg_index_matrix(pg: &Client, target: &TargetArgs, args: &BenchArgs, workload: &Workload, profiles: &[PgIndexProfile], report: &mut BenchmarkReport) -> Result<(), BenchmarkError> {
+    let profiles = if profiles.is_empty() { PgIndexProfile::value_variants() } else { profiles };
+    for profile in profiles {
+        info!("Switching PostgreSQL to index profile {}...", profile.label());
+        postgres::drop_indexes(pg, &target.table).await?;
+        report.indexes.extend(postgres::create_indexes(pg, &target.table, *profile).await?);
+        postgres::analyze_postgres(pg, &target.table).await?;
+
+        println!("\nPostgreSQL index profile: {}", profile.label());
+        let mut queries = bench_postgres(pg, target, args, workload).await?;
+        for query in &mut queries {
+            query.index_profile = Some(profile.label().to_string());
+        }
+        report.queries.extend(queries);
+        if args.explain {
+            let mut plans = postgres::explain_postgres(pg, &target.table, workload, args.count_totals).await?;
+            for plan in &mut plans {
+                plan.index_profile = Some(profile.label().to_string());
             }
-        };
-        let duration = start.elapsed();
-        total_latency += duration;
-        total_rows_found += rows.len();
-
-        println!(
-            "{:<25} | {:<10} | {:<15.4}",
-            query_desc,
-            rows.len(),
-            duration.as_secs_f64() * 1000.0
-        );
+            report.plans.extend(plans);
+        }
     }
 
-    let avg_latency = if query_count > 0 { total_latency / query_count as u32 } else { Duration::ZERO };
-    println!("{:-<60}", "");
-    println!(
-        "PostgreSQL Average Latency: {:.4}ms ({} queries, {} total results)",
-        avg_latency.as_secs_f64() * 1000.0,
-        query_count,
-        total_rows_found
-    );
+    // Leave the table indexed the way the target asks for
+    info!("Restoring PostgreSQL index profile {}...", target.pg_index_profile.label());
+    postgres::drop_indexes(pg, &target.table).await?;
+    postgres::create_indexes(pg, &target.table, target.pg_index_profile).await?;
     Ok(())
 }
 
-async fn benchmark_elasticsearch(client: &Elasticsearch, queries: &[(&str, Value)]) -> Result<(), BenchmarkError> {
-    println!("{:<25} | {:<10} | {:<15}", "Query Type", "Count", "Latency (ms)");
-    println!("{:-<60}", "");
-
-    let mut total_latency = Duration::ZERO;
-    let mut total_rows_found = 0;
-    let query_count = queries.len();
-
-    for (query_desc, es_query_json) in queries {
-        let start = Instant::now();
-        let response = client
-            .search(SearchParts::Index(&[ES_INDEX_NAME]))
-            .body(json!({
-                "_source": ["title"], // Only fetch title
-                "query": es_query_json, // Use the provided JSON query structure
-                "size": 10
-            }))
-            .send()
-            .await?;
-
-        let duration = start.elapsed();
-        total_latency += duration;
-
-        // Check HTTP status before parsing JSON
-        if !response.status_code().is_success() {
-            let status = response.status_code();
-             let error_body = response.text().await?;
-             println!("WARN: Elasticsearch query failed for '{}' - Status: {}, Body: {}", query_desc, status, error_body);
-             continue; // Skip this query
-        }
-
-        let response_body: Value = response.json().await?;
-        let hits = response_body["hits"]["hits"].as_array().map_or(0, |h| h.len());
-        total_rows_found += hits;
+// Size of the already-loaded dataset, for reports of runs that did not load it themselves
+async fn loaded_document_count(conns: &Connections, target: &TargetArgs) -> Result<usize, BenchmarkError> {
+    if let Some(es) = &conns.es {
+        return elastic::count_documents(es, &target.index).await;
+    }
+    if let Some(pg) = &conns.pg {
+        return postgres::count_documents(pg, &target.table).await;
+    }
+    Ok(0)
+}
 
+// Side-by-side disk footprint of the loaded table and index
+fn print_storage(storage: &[StorageReport]) {
+    if storage.is_empty() {
+        return;
+    }
+    println!("\n{:<14} | {:<30} | {:>12} | {:>12} | {:>12}", "Backend", "Table / Index", "Documents", "Size (MiB)", "Bytes/doc");
+    println!("{:-<92}", "");
+    for entry in storage {
+        let per_doc = if entry.documents > 0 { entry.total_bytes as f64 / entry.documents as f64 } else { 0.0 };
         println!(
-            "{:<25} | {:<10} | {:<15.4}",
-            query_desc,
-            hits,
-            duration.as_secs_f64() * 1000.0
+            "{:<14} | {:<30} | {:>12} | {:>12.1} | {:>12.1}",
+            entry.backend, entry.name, entry.documents, entry.total_bytes as f64 / (1024.0 * 1024.0), per_doc
         );
     }
+    println!("{:-<92}", "");
+}
 
-    let avg_latency = if query_count > 0 { total_latency / query_count as u32 } else { Duration::ZERO };
-    println!("{:-<60}", "");
-    println!(
-        "Elasticsearch Average Latency: {:.4}ms ({} queries, {} total results)",
-        avg_latency.as_secs_f64() * 1000.0,
-        query_count,
-        total_rows_found
-    );
+// --- Result Export ---
+
+fn export_report(report: &BenchmarkReport, args: &ReportArgs) -> Result<(), BenchmarkError> {
+    if let Some(path) = &args.report_json {
+        report.write_json(path)?;
+        info!("JSON report written to {}", path.display());
+    }
+    if let Some(path) = &args.report_csv {
+        report.write_csv(path)?;
+        info!("CSV report written to {}", path.display());
+    }
+    // Keep every run as a timestamped JSON file for later `compare`
+    if let Some(dir) = &args.results_dir {
+        let path = save_run(report, dir)?;
+        info!("Run saved to {}", path.display());
+    }
     Ok(())
 }
 
-// Add indicatif to Cargo.toml if not already present:
-// indicatif = { version = "0.17", features = ["tokio"] }
-// Add fake = { version = "2.5", features = ["chrono"] } or similar
-// Ensure chrono, serde, serde_json, tokio-postgres, elasticsearch, etc. are up-to-date.
+fn save_run(report: &BenchmarkReport, dir: &Path) -> Result<PathBuf, BenchmarkError> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("run-{}.json", report.started_at.format("%Y%m%dT%H%M%SZ")));
+    report.write_json(&path)?;
+    Ok(path)
+}
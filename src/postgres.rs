@@ -0,0 +1,751 @@
+// src/postgres.rs
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::time::{Duration, Instant};
+use bytes::{Bytes, BytesMut};
+use clap::ValueEnum;
+use futures_util::{pin_mut, SinkExt, TryStreamExt};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde_json::Value;
+use indicatif::ProgressBar;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::{Client, NoTls, Statement};
+use tracing::{error, info, warn};
+
+use crate::BenchmarkError;
+use crate::cli::{BenchArgs, DataArgs, ExistingMode};
+use crate::report::{Backend, IndexReport, QueryPlan, QueryReport, StorageReport};
+use crate::stats::{self, LatencyRecorder};
+use crate::stream::{self, DocumentStream};
+use crate::verify;
+use crate::workload::{PgParam, PgQuerySpec, QuerySpec, QueryVariant, Workload};
+use crate::writes::{WriteInput, WriteOp};
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+// --- Connection ---
+
+pub async fn connect_postgres() -> Result<Client, BenchmarkError> {
+    let db_url = env::var("DATABASE_URL")
+        .map_err(|_| BenchmarkError::EnvVar("DATABASE_URL".to_string()))?;
+    let (client, connection) = tokio_postgres::connect(&db_url, NoTls).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            error!("PostgreSQL connection error: {}", e);
+        }
+    });
+    Ok(client)
+}
+
+// Server versions are informational only, so failures are ignored
+pub async fn postgres_version(client: &Client) -> Option<String> {
+    let row = client.query_one("SHOW server_version", &[]).await.ok()?;
+    row.try_get(0).ok()
+}
+
+pub async fn count_documents(client: &Client, table: &str) -> Result<usize, BenchmarkError> {
+    let row = client.query_one(&format!("SELECT COUNT(*) FROM {table}"), &[]).await?;
+    Ok(row.get::<_, i64>(0) as usize)
+}
+
+// --- Setup ---
+
+pub async fn setup_postgres(client: &Client, table: &str, profile: PgIndexProfile, existing: ExistingMode) -> Result<(), BenchmarkError> {
+    if existing == ExistingMode::Recreate {
+        drop_postgres(client, table).await?;
+    }
+    // Create table with a single JSONB column
+    // Add a GIN index for efficient JSONB operations
+    client.batch_execute(&format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            id SERIAL PRIMARY KEY, -- Keep ID for potential reference
+            data JSONB NOT NULL
+        );
+        "#)
+    ).await?;
+    // Tables created before doc_id existed get it added, which rewrites every row they hold
+    let has_doc_id = client.query_one(
+        "SELECT EXISTS (SELECT 1 FROM pg_attribute WHERE attrelid = $1::text::regclass AND attname = 'doc_id' AND NOT attisdropped)",
+        &[&table],
+    ).await?.get::<_, bool>(0);
+    if !has_doc_id {
+        let rows = count_documents(client, table).await?;
+        if rows > 0 {
+            warn!("Adding the doc_id column to '{}' rewrites its {} existing rows; this may take a while.", table, rows);
+        }
+        let start = Instant::now();
+        // Document id shared with Elasticsearch (its _id); NULL for documents without one
+        client.batch_execute(&format!(
+            "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS doc_id TEXT GENERATED ALWAYS AS (data ->> 'id') STORED"
+        )).await?;
+        if rows > 0 {
+            info!("PostgreSQL table '{}' rewritten with doc_id in {:?}", table, start.elapsed());
+        }
+    }
+    client.batch_execute(&format!("CREATE UNIQUE INDEX IF NOT EXISTS {} {}", key_index_name(table), key_index_definition(table))).await?;
+    for (name, definition) in index_definitions(table, profile) {
+        client.batch_execute(&format!("CREATE INDEX IF NOT EXISTS {name} {definition}")).await?;
+    }
+    info!("PostgreSQL table '{}' with JSONB column and {} indexes checked/created.", table, profile.label());
+    if existing == ExistingMode::Truncate {
+        truncate_postgres(client, table).await?;
+    }
+    Ok(())
+}
+
+// Sets of secondary indexes to compare; each profile uses its own index names
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgIndexProfile {
+    /// No secondary indexes
+    None,
+    /// GIN on the whole document with the default jsonb_ops
+    JsonbOps,
+    /// GIN on the whole document with jsonb_path_ops (smaller, containment only)
+    JsonbPathOps,
+    /// GIN on the data -> 'tags' and data -> 'attributes' expressions
+    Expression,
+    /// B-tree expression indexes on attributes.att0 (numeric) and attributes.att2.nested_key
+    Btree,
+}
+
+impl PgIndexProfile {
+    pub fn label(self) -> &'static str {
+        match self {
+            PgIndexProfile::None => "none",
+            PgIndexProfile::JsonbOps => "jsonb-ops",
+            PgIndexProfile::JsonbPathOps => "jsonb-path-ops",
+            PgIndexProfile::Expression => "expression",
+            PgIndexProfile::Btree => "btree",
+        }
+    }
+}
+
+// Secondary indexes of a profile, as (name, definition)
+fn index_definitions(table: &str, profile: PgIndexProfile) -> Vec<(String, String)> {
+    match profile {
+        PgIndexProfile::None => vec![],
+        // A GIN index on the JSONB column. This is crucial for performance.
+        PgIndexProfile::JsonbOps => vec![
+            (format!("{table}_data_gin_idx"), format!("ON {table} USING GIN(data)")),
+        ],
+        PgIndexProfile::JsonbPathOps => vec![
+            (format!("{table}_data_gin_path_idx"), format!("ON {table} USING GIN (data jsonb_path_ops)")),
+        ],
+        // Index specific paths for very specific query patterns
+        PgIndexProfile::Expression => vec![
+            (format!("{table}_tags_gin_idx"), format!("ON {table} USING GIN ((data -> 'tags'))")),
+            (format!("{table}_attr_gin_idx"), format!("ON {table} USING GIN ((data -> 'attributes'))")),
+        ],
+        // The expressions must match the workload predicates exactly for the planner to use them
+        PgIndexProfile::Btree => vec![
+            (format!("{table}_att0_btree_idx"), format!("ON {table} (((data -> 'attributes' ->> 'att0')::numeric))")),
+            (format!("{table}_nested_key_btree_idx"), format!("ON {table} ((data -> 'attributes' -> 'att2' ->> 'nested_key'))")),
+        ],
+    }
+}
+
+// Unique index on doc_id that keeps ids unique and serves the writes by id. It is not part of any
+// profile: it stays in place for every profile and is only deferred with the others when loading.
+fn key_index_name(table: &str) -> String {
+    format!("{table}_doc_id_idx")
+}
+
+fn key_index_definition(table: &str) -> String {
+    format!("ON {table} (doc_id)")
+}
+
+// Build the doc_id index on the loaded table, timed like the secondary indexes
+pub async fn create_key_index(client: &Client, table: &str) -> Result<IndexReport, BenchmarkError> {
+    let name = key_index_name(table);
+    let start = Instant::now();
+    client.batch_execute(&format!("CREATE UNIQUE INDEX IF NOT EXISTS {name} {}", key_index_definition(table))).await?;
+    let duration = start.elapsed();
+    let size_bytes = relation_size(client, &name).await?.unwrap_or(0);
+    info!("PostgreSQL index '{}' built in {:?} ({:.1} MiB)", name, duration, size_bytes as f64 / MIB);
+    Ok(IndexReport::new(Backend::Postgres, &name, Some(duration), size_bytes))
+}
+
+pub async fn drop_key_index(client: &Client, table: &str) -> Result<(), BenchmarkError> {
+    client.batch_execute(&format!("DROP INDEX IF EXISTS {}", key_index_name(table))).await?;
+    Ok(())
+}
+
+// Drop every secondary index on the table, leaving only the primary key, constraint indexes and the doc_id
+// index. Indexes come from the catalog rather than the profiles, so leftovers such as the GIN index of
+// tables created by older versions cannot skew the profiles measured afterwards.
+pub async fn drop_indexes(client: &Client, table: &str) -> Result<(), BenchmarkError> {
+    let rows = client.query(
+        "SELECT i.indexrelid::regclass::text, c.relname FROM pg_index i JOIN pg_class c ON c.oid = i.indexrelid \
+         WHERE i.indrelid = $1::text::regclass AND c.relname <> $2 \
+           AND NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conindid = i.indexrelid)",
+        &[&table, &key_index_name(table)],
+    ).await?;
+    let known: Vec<String> = PgIndexProfile::value_variants()
+        .iter()
+        .flat_map(|profile| index_definitions(table, *profile))
+        .map(|(name, _)| name)
+        .collect();
+    for row in rows {
+        let (index, name): (String, String) = (row.get(0), row.get(1));
+        if !known.contains(&name) {
+            warn!("Dropping index '{}' on '{}', which belongs to no index profile.", name, table);
+        }
+        client.batch_execute(&format!("DROP INDEX IF EXISTS {index}")).await?;
+    }
+    info!("PostgreSQL secondary indexes on '{}' dropped.", table);
+    Ok(())
+}
+
+// Build the secondary indexes of a profile on the loaded table, timing each one
+pub async fn create_indexes(client: &Client, table: &str, profile: PgIndexProfile) -> Result<Vec<IndexReport>, BenchmarkError> {
+    let mut reports = Vec::new();
+    for (name, definition) in index_definitions(table, profile) {
+        let start = Instant::now();
+        client.batch_execute(&format!("CREATE INDEX IF NOT EXISTS {name} {definition}")).await?;
+        let duration = start.elapsed();
+        let size_bytes = relation_size(client, &name).await?.unwrap_or(0);
+        info!("PostgreSQL index '{}' built in {:?} ({:.1} MiB)", name, duration, size_bytes as f64 / MIB);
+        reports.push(IndexReport::new(Backend::Postgres, &name, Some(duration), size_bytes));
+    }
+    Ok(reports)
+}
+
+// Refresh planner statistics; expression indexes get their own statistics only from ANALYZE
+pub async fn analyze_postgres(client: &Client, table: &str) -> Result<(), BenchmarkError> {
+    client.batch_execute(&format!("ANALYZE {table}")).await?;
+    Ok(())
+}
+
+// Sizes of the doc_id and secondary indexes as they are now, for loads that maintained them while inserting
+pub async fn index_sizes(client: &Client, table: &str, profile: PgIndexProfile) -> Result<Vec<IndexReport>, BenchmarkError> {
+    let mut reports = Vec::new();
+    let names = index_definitions(table, profile).into_iter().map(|(name, _)| name);
+    for name in std::iter::once(key_index_name(table)).chain(names) {
+        if let Some(size_bytes) = relation_size(client, &name).await? {
+            info!("PostgreSQL index '{}' size: {:.1} MiB", name, size_bytes as f64 / MIB);
+            reports.push(IndexReport::new(Backend::Postgres, &name, None, size_bytes));
+        }
+    }
+    Ok(reports)
+}
+
+// On-disk size of a table or index; None when it does not exist
+async fn relation_size(client: &Client, name: &str) -> Result<Option<u64>, BenchmarkError> {
+    let row = client.query_one("SELECT pg_relation_size(to_regclass($1))", &[&name]).await?;
+    Ok(row.get::<_, Option<i64>>(0).map(|size| size as u64))
+}
+
+// Empty the table between insert strategies so each one loads into the same starting state
+pub async fn truncate_postgres(client: &Client, table: &str) -> Result<(), BenchmarkError> {
+    client.batch_execute(&format!("TRUNCATE TABLE {table} RESTART IDENTITY")).await?;
+    info!("PostgreSQL table '{}' truncated.", table);
+    Ok(())
+}
+
+pub async fn drop_postgres(client: &Client, table: &str) -> Result<(), BenchmarkError> {
+    client.batch_execute(&format!("DROP TABLE IF EXISTS {table}")).await?;
+    info!("PostgreSQL table '{}' dropped.", table);
+    Ok(())
+}
+
+// --- Insertion ---
+
+// How documents get into the table; every strategy loads the same stream so they can be compared
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgInsertStrategy {
+    /// Prepared single-row INSERT per document
+    Insert,
+    /// Multi-row INSERT ... VALUES with --batch-size rows per statement
+    InsertBatch,
+    /// INSERT ... SELECT FROM UNNEST($1::jsonb[]) with --batch-size documents per array
+    Unnest,
+    /// COPY ... FROM STDIN in text format
+    CopyText,
+    /// COPY ... FROM STDIN (FORMAT BINARY)
+    CopyBinary,
+}
+
+impl PgInsertStrategy {
+    pub fn label(self) -> &'static str {
+        match self {
+            PgInsertStrategy::Insert => "insert",
+            PgInsertStrategy::InsertBatch => "insert_batch",
+            PgInsertStrategy::Unnest => "unnest",
+            PgInsertStrategy::CopyText => "copy_text",
+            PgInsertStrategy::CopyBinary => "copy_binary",
+        }
+    }
+}
+
+// Streams documents into the table over one COPY; returns how many were written
+pub async fn insert_postgres(client: &Client, table: &str, mut docs: DocumentStream, pb: &ProgressBar) -> Result<usize, BenchmarkError> {
+    // Use COPY BINARY for efficient bulk insertion of JSONB
+    let copy_stmt = format!(
+        // Copy into the 'data' column
+        "COPY {table} (data) FROM STDIN (FORMAT BINARY)"
+    );
+
+    let sink = client.copy_in(&copy_stmt).await?;
+
+    // The type for the 'data' column is JSONB
+    let types = &[Type::JSONB];
+    let writer = BinaryCopyInWriter::new(sink, types);
+    pin_mut!(writer);
+
+    // Write documents as the producer hands them over; nothing is buffered beyond the channel
+    // serde_json::Value implements ToSql for JSONB
+    let mut written = 0;
+    while let Some(doc_value) = docs.recv().await {
+        let doc_value = doc_value?;
+        // write expects a slice of references implementing ToSql
+        writer.as_mut().write(&[&doc_value]).await?;
+        written += 1;
+        pb.inc(1);
+    }
+
+    // Finish the COPY operation
+    writer.finish().await?;
+
+    Ok(written)
+}
+
+// COPY in text format: one JSON document per line, escaped for the text format
+pub async fn insert_postgres_copy_text(client: &Client, table: &str, mut docs: DocumentStream, pb: &ProgressBar, batch_size: usize) -> Result<usize, BenchmarkError> {
+    let sink = client.copy_in::<_, Bytes>(&format!("COPY {table} (data) FROM STDIN")).await?;
+    pin_mut!(sink);
+
+    let mut written = 0;
+    let mut pending = 0;
+    let mut buf = BytesMut::new();
+    while let Some(doc_value) = docs.recv().await {
+        // Compact JSON has no raw newlines or tabs, so backslashes are the only thing to escape
+        let line = serde_json::to_string(&doc_value?)?.replace('\\', "\\\\");
+        buf.extend_from_slice(line.as_bytes());
+        buf.extend_from_slice(b"\n");
+        pending += 1;
+        if pending == batch_size {
+            sink.as_mut().send(buf.split().freeze()).await?;
+            written += pending;
+            pb.inc(pending as u64);
+            pending = 0;
+        }
+    }
+    if pending > 0 {
+        sink.as_mut().send(buf.split().freeze()).await?;
+        written += pending;
+        pb.inc(pending as u64);
+    }
+    sink.finish().await?;
+    Ok(written)
+}
+
+// One prepared INSERT per document, the way most services write
+pub async fn insert_postgres_rows(client: &Client, table: &str, mut docs: DocumentStream, pb: &ProgressBar) -> Result<usize, BenchmarkError> {
+    let statement = client.prepare(&format!("INSERT INTO {table} (data) VALUES ($1)")).await?;
+    let mut written = 0;
+    while let Some(doc_value) = docs.recv().await {
+        client.execute(&statement, &[&doc_value?]).await?;
+        written += 1;
+        pb.inc(1);
+    }
+    Ok(written)
+}
+
+// Multi-row INSERT ... VALUES ($1), ($2), ...; the full-size statement is prepared once
+pub async fn insert_postgres_batches(client: &Client, table: &str, mut docs: DocumentStream, pb: &ProgressBar, batch_size: usize) -> Result<usize, BenchmarkError> {
+    // Bind parameters are limited to 65535 per statement
+    let batch_size = batch_size.min(u16::MAX as usize);
+    let values_sql = |rows: usize| {
+        let placeholders: Vec<String> = (1..=rows).map(|i| format!("(${i})")).collect();
+        format!("INSERT INTO {table} (data) VALUES {}", placeholders.join(", "))
+    };
+    let full_batch = client.prepare(&values_sql(batch_size)).await?;
+
+    let mut written = 0;
+    let mut batch: Vec<Value> = Vec::with_capacity(batch_size);
+    loop {
+        let doc_value = docs.recv().await;
+        let done = doc_value.is_none();
+        if let Some(doc_value) = doc_value {
+            batch.push(doc_value?);
+        }
+        if batch.len() == batch_size || (done && !batch.is_empty()) {
+            let params: Vec<&(dyn ToSql + Sync)> = batch.iter().map(|d| d as &(dyn ToSql + Sync)).collect();
+            if batch.len() == batch_size {
+                client.execute(&full_batch, &params).await?;
+            } else {
+                client.execute(&values_sql(batch.len()), &params).await?;
+            }
+            written += batch.len();
+            pb.inc(batch.len() as u64);
+            batch.clear();
+        }
+        if done {
+            return Ok(written);
+        }
+    }
+}
+
+// INSERT ... SELECT FROM UNNEST over a jsonb[] parameter: one statement, one parameter per batch
+pub async fn insert_postgres_unnest(client: &Client, table: &str, mut docs: DocumentStream, pb: &ProgressBar, batch_size: usize) -> Result<usize, BenchmarkError> {
+    let statement = client.prepare(&format!("INSERT INTO {table} (data) SELECT * FROM UNNEST($1::jsonb[])")).await?;
+    let mut written = 0;
+    let mut batch: Vec<Value> = Vec::with_capacity(batch_size);
+    loop {
+        let doc_value = docs.recv().await;
+        let done = doc_value.is_none();
+        if let Some(doc_value) = doc_value {
+            batch.push(doc_value?);
+        }
+        if batch.len() == batch_size || (done && !batch.is_empty()) {
+            client.execute(&statement, &[&batch]).await?;
+            written += batch.len();
+            pb.inc(batch.len() as u64);
+            batch.clear();
+        }
+        if done {
+            return Ok(written);
+        }
+    }
+}
+
+async fn run_insert_strategy(client: &Client, table: &str, strategy: PgInsertStrategy, docs: DocumentStream, pb: &ProgressBar, batch_size: usize) -> Result<usize, BenchmarkError> {
+    let batch_size = batch_size.max(1);
+    match strategy {
+        PgInsertStrategy::Insert => insert_postgres_rows(client, table, docs, pb).await,
+        PgInsertStrategy::InsertBatch => insert_postgres_batches(client, table, docs, pb, batch_size).await,
+        PgInsertStrategy::Unnest => insert_postgres_unnest(client, table, docs, pb, batch_size).await,
+        PgInsertStrategy::CopyText => insert_postgres_copy_text(client, table, docs, pb, batch_size).await,
+        PgInsertStrategy::CopyBinary => insert_postgres(client, table, docs, pb).await,
+    }
+}
+
+// Outcome of one shard of a parallel load
+pub struct ShardResult {
+    pub shard: usize,
+    pub documents: usize,
+    pub duration: Duration,
+}
+
+// Spreads the documents round-robin over `shards` connections, each loading its share with
+// `strategy`. Shard channels are bounded too, so backpressure still reaches the producer.
+pub async fn insert_postgres_sharded(table: &str, strategy: PgInsertStrategy, mut docs: DocumentStream, expected_len: Option<u64>, shards: usize, settings: &DataArgs) -> Result<Vec<ShardResult>, BenchmarkError> {
+    info!("Starting PostgreSQL {} load over {} connection(s)...", strategy.label(), shards);
+    let (buffer, batch_size) = (settings.stream_buffer, settings.batch_size);
+    let pb = stream::progress_bar(expected_len);
+
+    // Connect every shard before any of them starts its clock, so no shard is timed waiting for the others
+    let mut clients = Vec::with_capacity(shards);
+    for _ in 0..shards {
+        clients.push(connect_postgres().await?);
+    }
+
+    let mut senders = Vec::with_capacity(shards);
+    let mut tasks = JoinSet::new();
+    for (shard, client) in clients.into_iter().enumerate() {
+        let (tx, rx) = mpsc::channel(buffer.max(1));
+        senders.push(tx);
+        let table = table.to_string();
+        let pb = pb.clone();
+        tasks.spawn(async move {
+            let start = Instant::now();
+            let documents = run_insert_strategy(&client, &table, strategy, rx, &pb, batch_size).await?;
+            Ok::<_, BenchmarkError>(ShardResult { shard, documents, duration: start.elapsed() })
+        });
+    }
+
+    // A failed shard drops its receiver; stop dispatching and let its error surface below
+    let mut next = 0;
+    while let Some(doc) = docs.recv().await {
+        if senders[next].send(doc).await.is_err() {
+            break;
+        }
+        next = (next + 1) % shards;
+    }
+    drop(senders);
+
+    let mut results = Vec::with_capacity(shards);
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok(result) => results.push(result?),
+            Err(e) => return Err(BenchmarkError::Ingest(format!("{} shard panicked: {}", strategy.label(), e))),
+        }
+    }
+    pb.finish_with_message(format!("PostgreSQL {} complete", strategy.label()));
+    results.sort_by_key(|r| r.shard);
+    Ok(results)
+}
+
+// Table, TOAST and index sizes of the documents table
+pub async fn storage_report(client: &Client, table: &str) -> Result<StorageReport, BenchmarkError> {
+    let row = client.query_one(
+        "SELECT pg_total_relation_size(c.oid), pg_relation_size(c.oid), \
+                COALESCE(pg_total_relation_size(NULLIF(c.reltoastrelid, 0)), 0), pg_indexes_size(c.oid) \
+         FROM pg_class c WHERE c.oid = $1::text::regclass",
+        &[&table],
+    ).await?;
+    let mut metrics = BTreeMap::new();
+    metrics.insert("heap_bytes".to_string(), row.get::<_, i64>(1) as u64);
+    metrics.insert("toast_bytes".to_string(), row.get::<_, i64>(2) as u64);
+    metrics.insert("indexes_bytes".to_string(), row.get::<_, i64>(3) as u64);
+
+    let index_rows = client.query(
+        "SELECT indexrelid::regclass::text, pg_relation_size(indexrelid) FROM pg_index WHERE indrelid = $1::text::regclass",
+        &[&table],
+    ).await?;
+    for index_row in index_rows {
+        metrics.insert(format!("{}_bytes", index_row.get::<_, String>(0)), index_row.get::<_, i64>(1) as u64);
+    }
+
+    let total_bytes = row.get::<_, i64>(0) as u64;
+    info!(
+        "PostgreSQL table '{}' storage: {:.1} MiB total ({:.1} MiB heap, {:.1} MiB TOAST, {:.1} MiB indexes)",
+        table, total_bytes as f64 / MIB, metrics["heap_bytes"] as f64 / MIB,
+        metrics["toast_bytes"] as f64 / MIB, metrics["indexes_bytes"] as f64 / MIB
+    );
+    Ok(StorageReport {
+        backend: Backend::Postgres,
+        name: table.to_string(),
+        documents: count_documents(client, table).await? as u64,
+        total_bytes,
+        metrics,
+    })
+}
+
+// --- Queries ---
+
+// A workload query prepared on one PG connection
+pub struct PgPreparedQuery {
+    pub name: String,
+    pub weight: u32,
+    variant: QueryVariant,
+    sql: String,
+    statement: Statement,
+    spec: QuerySpec,
+    pg: PgQuerySpec,
+}
+
+impl PgPreparedQuery {
+    // Draws fresh values for the sampled parameters of the query
+    pub fn bind(&self, rng: &mut impl Rng) -> Result<Vec<PgParam>, BenchmarkError> {
+        let values = self.spec.sample_params(rng);
+        self.spec.pg_params(&self.pg, &values, rng)
+    }
+}
+
+// Prepare every PG query in the workload once so iterations only pay for execution
+pub async fn prepare_pg_queries(client: &Client, table: &str, workload: &Workload, count_totals: bool) -> Result<Vec<PgPreparedQuery>, BenchmarkError> {
+    let mut prepared = Vec::new();
+    for (variant, spec, pg) in workload.pg_variants(count_totals) {
+        let predicate = pg.predicate.trim();
+        let sql = match variant {
+            QueryVariant::Page => format!("SELECT data ->> 'title' FROM {table} WHERE {predicate} LIMIT {limit}", limit = workload.limit),
+            QueryVariant::Count => format!("SELECT COUNT(*) FROM {table} WHERE {predicate}"),
+        };
+        let statement = client.prepare(&sql).await.map_err(|e| {
+            BenchmarkError::Workload(format!("failed to prepare PG query '{}': {}", spec.name, e))
+        })?;
+        prepared.push(PgPreparedQuery {
+            name: variant.query_name(&spec.name),
+            weight: spec.weight,
+            variant,
+            sql,
+            statement,
+            spec: spec.clone(),
+            pg: pg.clone(),
+        });
+    }
+    Ok(prepared)
+}
+
+// Run a single PG query from the suite with freshly drawn parameters; returns the rows it produced,
+// or the total for count variants
+pub async fn run_pg_query(client: &Client, query: &PgPreparedQuery, rng: &mut impl Rng) -> Result<usize, BenchmarkError> {
+    let bound = query.bind(rng)?;
+    let params: Vec<&(dyn ToSql + Sync)> = bound.iter().map(PgParam::as_sql).collect();
+    let rows = client.query(&query.statement, &params).await?;
+    Ok(match query.variant {
+        QueryVariant::Page => rows.len(),
+        QueryVariant::Count => rows.first().map_or(0, |row| row.get::<_, i64>(0) as usize),
+    })
+}
+
+// Keys of every row matching a workload predicate, without the LIMIT of the benchmark queries, and the
+// number of rows. Rows are streamed and only their key is kept: the doc_id, or a fingerprint of the data
+// for rows without one.
+pub async fn matching_keys(client: &Client, table: &str, pg: &PgQuerySpec, params: &[PgParam]) -> Result<(usize, BTreeSet<String>), BenchmarkError> {
+    let sql = format!(
+        "SELECT doc_id, CASE WHEN doc_id IS NULL THEN data END FROM {table} WHERE {predicate}",
+        predicate = pg.predicate.trim()
+    );
+    let rows = client.query_raw(&sql, params.iter().map(PgParam::as_sql)).await?;
+    pin_mut!(rows);
+    let mut count = 0;
+    let mut keys = BTreeSet::new();
+    while let Some(row) = rows.try_next().await? {
+        count += 1;
+        keys.insert(match row.get::<_, Option<String>>(0) {
+            Some(id) => id,
+            None => verify::fingerprint(&row.get(1)),
+        });
+    }
+    Ok((count, keys))
+}
+
+// Title of a document, for logging the few documents a verification disagrees on
+pub async fn document_title(client: &Client, table: &str, id: &str) -> Result<Option<String>, BenchmarkError> {
+    let row = client.query_opt(&format!("SELECT data ->> 'title' FROM {table} WHERE doc_id = $1"), &[&id]).await?;
+    Ok(row.and_then(|row| row.get(0)))
+}
+
+// --- Writes ---
+
+// Prepared statements of the write benchmarks
+pub struct PgWriteStatements {
+    pub table: String,
+    set_attribute: Statement,
+    increment_attribute: Statement,
+    delete_by_id: Statement,
+    delete_by_query: Statement,
+}
+
+pub async fn prepare_pg_writes(client: &Client, table: &str) -> Result<PgWriteStatements, BenchmarkError> {
+    Ok(PgWriteStatements {
+        table: table.to_string(),
+        set_attribute: client.prepare(&format!(
+            "UPDATE {table} SET data = jsonb_set(data, '{{attributes,att0}}', to_jsonb($2::bigint)) WHERE doc_id = $1"
+        )).await?,
+        increment_attribute: client.prepare(&format!(
+            "UPDATE {table} SET data = jsonb_set(data, '{{attributes,att0}}', to_jsonb((data -> 'attributes' ->> 'att0')::bigint + $2::bigint)) WHERE doc_id = $1"
+        )).await?,
+        delete_by_id: client.prepare(&format!("DELETE FROM {table} WHERE doc_id = $1")).await?,
+        delete_by_query: client.prepare(&format!(
+            "DELETE FROM {table} WHERE (data -> 'attributes' ->> 'att0')::numeric = $1::bigint"
+        )).await?,
+    })
+}
+
+// Runs one write and returns how many rows it changed
+pub async fn run_pg_write(client: &Client, statements: &PgWriteStatements, op: WriteOp, input: &WriteInput<'_>) -> Result<u64, BenchmarkError> {
+    let changed = match op {
+        WriteOp::SetAttribute => client.execute(&statements.set_attribute, &[&input.id, &input.value]).await?,
+        WriteOp::IncrementAttribute => client.execute(&statements.increment_attribute, &[&input.id, &input.value]).await?,
+        WriteOp::DeleteById => client.execute(&statements.delete_by_id, &[&input.id]).await?,
+        WriteOp::DeleteByQuery => client.execute(&statements.delete_by_query, &[&input.value]).await?,
+    };
+    Ok(changed)
+}
+
+// Random document ids to run writes against
+pub async fn sample_doc_ids(client: &Client, table: &str, count: usize) -> Result<Vec<String>, BenchmarkError> {
+    let rows = client.query(
+        &format!("SELECT doc_id FROM {table} WHERE doc_id IS NOT NULL ORDER BY random() LIMIT $1"),
+        &[&(count as i64)],
+    ).await?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+// EXPLAIN (ANALYZE, BUFFERS) every query of the suite once, outside of the timed runs
+pub async fn explain_postgres(client: &Client, table: &str, workload: &Workload, count_totals: bool) -> Result<Vec<QueryPlan>, BenchmarkError> {
+    println!("{:<30} | {:>10} | {:>10} | {:>10} | {:>10} | {:<40} | Indexes", "Query Type", "plan ms", "exec ms", "buf hit", "buf read", "Scans");
+    println!("{:-<150}", "");
+
+    let mut plans = Vec::new();
+    let mut rng = StdRng::from_os_rng();
+    for query in prepare_pg_queries(client, table, workload, count_totals).await? {
+        let bound = query.bind(&mut rng)?;
+        let params: Vec<&(dyn ToSql + Sync)> = bound.iter().map(PgParam::as_sql).collect();
+        let row = client.query_one(&format!("EXPLAIN (ANALYZE, BUFFERS, FORMAT JSON) {}", query.sql), &params).await?;
+        let explain: Value = row.get(0);
+        let root = &explain[0];
+
+        let mut scan_nodes = Vec::new();
+        let mut indexes_used = Vec::new();
+        collect_plan_nodes(&root["Plan"], &mut scan_nodes, &mut indexes_used);
+        let plan = QueryPlan {
+            backend: Backend::Postgres,
+            name: query.name.clone(),
+            index_profile: None,
+            planning_ms: root["Planning Time"].as_f64().unwrap_or(0.0),
+            execution_ms: root["Execution Time"].as_f64().unwrap_or(0.0),
+            scan_nodes,
+            indexes_used,
+            // Buffer counts of the root node include all of its children
+            shared_hit_blocks: root["Plan"]["Shared Hit Blocks"].as_u64().unwrap_or(0),
+            shared_read_blocks: root["Plan"]["Shared Read Blocks"].as_u64().unwrap_or(0),
+            plan: root["Plan"].clone(),
+        };
+        println!(
+            "{:<30} | {:>10.3} | {:>10.3} | {:>10} | {:>10} | {:<40} | {}",
+            plan.name, plan.planning_ms, plan.execution_ms, plan.shared_hit_blocks, plan.shared_read_blocks,
+            plan.scan_nodes.join(", "), plan.indexes_used.join(", ")
+        );
+        plans.push(plan);
+    }
+    println!("{:-<150}", "");
+    Ok(plans)
+}
+
+fn collect_plan_nodes(node: &Value, scan_nodes: &mut Vec<String>, indexes_used: &mut Vec<String>) {
+    if let Some(node_type) = node["Node Type"].as_str() {
+        if node_type.contains("Scan") {
+            scan_nodes.push(node_type.to_string());
+        }
+    }
+    if let Some(index) = node["Index Name"].as_str() {
+        if !indexes_used.iter().any(|i| i == index) {
+            indexes_used.push(index.to_string());
+        }
+    }
+    for child in node["Plans"].as_array().into_iter().flatten() {
+        collect_plan_nodes(child, scan_nodes, indexes_used);
+    }
+}
+
+pub async fn benchmark_postgres(client: &Client, table: &str, workload: &Workload, settings: &BenchArgs) -> Result<Vec<QueryReport>, BenchmarkError> {
+    info!("{} warmup + {} measured iterations per query", settings.warmup, settings.iterations);
+    stats::print_stats_header();
+
+    let mut total_mean_ms = 0.0;
+    let mut total_rows_found = 0;
+    let mut reports = Vec::new();
+
+    let queries = prepare_pg_queries(client, table, workload, settings.count_totals).await?;
+    let query_count = queries.len();
+    let mut rng = StdRng::from_os_rng();
+
+    for query in &queries {
+        for _ in 0..settings.warmup {
+            run_pg_query(client, query, &mut rng).await?;
+        }
+
+        let mut recorder = LatencyRecorder::new();
+        let mut rows_found = 0;
+        for _ in 0..settings.iterations {
+            let start = Instant::now();
+            rows_found = run_pg_query(client, query, &mut rng).await?;
+            recorder.record(start.elapsed());
+        }
+
+        let query_stats = recorder.stats();
+        total_mean_ms += query_stats.mean_ms;
+        total_rows_found += rows_found;
+        stats::print_stats_row(&query.name, rows_found, &query_stats);
+        reports.push(QueryReport::sequential(Backend::Postgres, &query.name, rows_found, query_stats));
+    }
+
+    let avg_latency_ms = if query_count > 0 { total_mean_ms / query_count as f64 } else { 0.0 };
+    println!("{:-<150}", "");
+    println!(
+        "PostgreSQL Average Latency: {:.4}ms ({} queries, {} total results)",
+        avg_latency_ms,
+        query_count,
+        total_rows_found
+    );
+    Ok(reports)
+}
@@ -0,0 +1,406 @@
+// src/report.rs
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::BenchmarkError;
+use crate::stats::LatencyStats;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Postgres,
+    Elasticsearch,
+}
+
+impl Backend {
+    // Human-readable name for log lines
+    pub fn label(&self) -> &'static str {
+        match self {
+            Backend::Postgres => "PostgreSQL",
+            Backend::Elasticsearch => "Elasticsearch",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // pad() so table column widths apply
+        f.pad(match self {
+            Backend::Postgres => "postgres",
+            Backend::Elasticsearch => "elasticsearch",
+        })
+    }
+}
+
+// Where and with what the run was executed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Environment {
+    pub crate_version: String,
+    pub os: String,
+    pub arch: String,
+    pub hostname: Option<String>,
+    pub cpus: usize,
+    pub postgres_version: Option<String>,
+    pub elasticsearch_version: Option<String>,
+}
+
+impl Environment {
+    pub fn detect() -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            hostname: std::env::var("HOSTNAME").ok(),
+            cpus: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            postgres_version: None,
+            elasticsearch_version: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PhaseTiming {
+    pub name: String,
+    pub duration_ms: f64,
+}
+
+// Throughput of one ingest stream, or the total over all streams of a load
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IngestReport {
+    pub backend: Backend,
+    pub name: String,
+    pub documents: usize,
+    pub duration_ms: f64,
+    pub docs_per_sec: f64,
+    // Latency of the individual write requests, for loads made of many requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_latency: Option<LatencyStats>,
+    // Documents the backend rejected for good, and how many times requests were retried
+    #[serde(default)]
+    pub failed_documents: usize,
+    #[serde(default)]
+    pub retries: usize,
+}
+
+impl IngestReport {
+    pub fn new(backend: Backend, name: &str, documents: usize, duration: Duration) -> Self {
+        let secs = duration.as_secs_f64();
+        Self {
+            backend,
+            name: name.to_string(),
+            documents,
+            duration_ms: secs * 1000.0,
+            docs_per_sec: if secs > 0.0 { documents as f64 / secs } else { 0.0 },
+            request_latency: None,
+            failed_documents: 0,
+            retries: 0,
+        }
+    }
+}
+
+// One secondary index after a load; build time is only known when it was built after the data
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexReport {
+    pub backend: Backend,
+    pub name: String,
+    pub build_ms: Option<f64>,
+    pub size_bytes: u64,
+}
+
+impl IndexReport {
+    pub fn new(backend: Backend, name: &str, build_time: Option<Duration>, size_bytes: u64) -> Self {
+        Self {
+            backend,
+            name: name.to_string(),
+            build_ms: build_time.map(|d| d.as_secs_f64() * 1000.0),
+            size_bytes,
+        }
+    }
+}
+
+// On-disk footprint of one table or index after a load. Which metrics exist depends on the
+// backend; sizes end in `_bytes`, everything else is a count.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StorageReport {
+    pub backend: Backend,
+    pub name: String,
+    pub documents: u64,
+    pub total_bytes: u64,
+    pub metrics: BTreeMap<String, u64>,
+}
+
+// Execution plan of one query, with the figures that explain its latency
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueryPlan {
+    pub backend: Backend,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_profile: Option<String>,
+    pub planning_ms: f64,
+    pub execution_ms: f64,
+    // Scan nodes of the plan, e.g. "Seq Scan" or "Bitmap Index Scan"
+    pub scan_nodes: Vec<String>,
+    pub indexes_used: Vec<String>,
+    pub shared_hit_blocks: u64,
+    pub shared_read_blocks: u64,
+    pub plan: serde_json::Value,
+}
+
+// Statistics for one query on one backend
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueryReport {
+    pub backend: Backend,
+    pub name: String,
+    pub mode: String,
+    pub result_count: usize,
+    pub errors: usize,
+    pub throughput_qps: f64,
+    pub latency: LatencyStats,
+    // PostgreSQL index profile the query ran under, for index matrix runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_profile: Option<String>,
+}
+
+impl QueryReport {
+    // Queries run back to back, so throughput is the inverse of the mean latency
+    pub fn sequential(backend: Backend, name: &str, result_count: usize, latency: LatencyStats) -> Self {
+        let throughput_qps = if latency.mean_ms > 0.0 { 1000.0 / latency.mean_ms } else { 0.0 };
+        Self {
+            backend,
+            name: name.to_string(),
+            mode: "sequential".to_string(),
+            result_count,
+            errors: 0,
+            throughput_qps,
+            latency,
+            index_profile: None,
+        }
+    }
+
+    // Mode plus the index profile, when the query ran as part of an index matrix
+    pub fn mode_label(&self) -> String {
+        match &self.index_profile {
+            Some(profile) => format!("{} [{}]", self.mode, profile),
+            None => self.mode.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BenchmarkReport {
+    pub started_at: DateTime<Utc>,
+    pub environment: Environment,
+    pub workload: String,
+    pub data_count: usize,
+    // Generator seed, when this run generated its own data
+    #[serde(default)]
+    pub seed: Option<u64>,
+    pub phases: Vec<PhaseTiming>,
+    #[serde(default)]
+    pub ingest: Vec<IngestReport>,
+    #[serde(default)]
+    pub indexes: Vec<IndexReport>,
+    #[serde(default)]
+    pub storage: Vec<StorageReport>,
+    #[serde(default)]
+    pub plans: Vec<QueryPlan>,
+    #[serde(default)]
+    pub verification: Vec<QueryVerification>,
+    pub queries: Vec<QueryReport>,
+}
+
+// Whether PostgreSQL and Elasticsearch match the same documents for one draw of a query's parameters
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueryVerification {
+    pub name: String,
+    pub params: BTreeMap<String, serde_json::Value>,
+    pub postgres_count: usize,
+    // Total hits as reported by Elasticsearch
+    pub elasticsearch_count: usize,
+    // Matched documents missing from the other backend's result
+    pub only_postgres: usize,
+    pub only_elasticsearch: usize,
+}
+
+impl QueryVerification {
+    pub fn matches(&self) -> bool {
+        self.postgres_count == self.elasticsearch_count && self.only_postgres == 0 && self.only_elasticsearch == 0
+    }
+}
+
+// Flat row layout for the CSV export; phase and ingest rows leave the query columns empty
+#[derive(Serialize, Default)]
+struct CsvRow<'a> {
+    kind: &'a str,
+    backend: Option<Backend>,
+    name: &'a str,
+    mode: Option<&'a str>,
+    index_profile: Option<&'a str>,
+    duration_ms: Option<f64>,
+    documents: Option<usize>,
+    docs_per_sec: Option<f64>,
+    size_bytes: Option<u64>,
+    shared_hit_blocks: Option<u64>,
+    shared_read_blocks: Option<u64>,
+    indexes_used: Option<String>,
+    result_count: Option<usize>,
+    errors: Option<usize>,
+    retries: Option<usize>,
+    samples: Option<u64>,
+    throughput_qps: Option<f64>,
+    min_ms: Option<f64>,
+    mean_ms: Option<f64>,
+    stddev_ms: Option<f64>,
+    p50_ms: Option<f64>,
+    p90_ms: Option<f64>,
+    p99_ms: Option<f64>,
+    p999_ms: Option<f64>,
+    max_ms: Option<f64>,
+}
+
+impl BenchmarkReport {
+    pub fn new(workload: &str, data_count: usize) -> Self {
+        Self {
+            started_at: Utc::now(),
+            environment: Environment::detect(),
+            workload: workload.to_string(),
+            data_count,
+            seed: None,
+            phases: Vec::new(),
+            ingest: Vec::new(),
+            indexes: Vec::new(),
+            storage: Vec::new(),
+            plans: Vec::new(),
+            verification: Vec::new(),
+            queries: Vec::new(),
+        }
+    }
+
+    pub fn record_phase(&mut self, name: &str, duration: Duration) {
+        self.phases.push(PhaseTiming { name: name.to_string(), duration_ms: duration.as_secs_f64() * 1000.0 });
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<(), BenchmarkError> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn write_csv(&self, path: &Path) -> Result<(), BenchmarkError> {
+        let mut writer = csv::Writer::from_path(path)?;
+        for phase in &self.phases {
+            writer.serialize(CsvRow {
+                kind: "phase",
+                name: &phase.name,
+                duration_ms: Some(phase.duration_ms),
+                ..CsvRow::default()
+            })?;
+        }
+        for ingest in &self.ingest {
+            writer.serialize(CsvRow {
+                kind: "ingest",
+                backend: Some(ingest.backend),
+                name: &ingest.name,
+                duration_ms: Some(ingest.duration_ms),
+                documents: Some(ingest.documents),
+                docs_per_sec: Some(ingest.docs_per_sec),
+                errors: Some(ingest.failed_documents),
+                retries: Some(ingest.retries),
+                samples: ingest.request_latency.as_ref().map(|l| l.samples),
+                min_ms: ingest.request_latency.as_ref().map(|l| l.min_ms),
+                mean_ms: ingest.request_latency.as_ref().map(|l| l.mean_ms),
+                stddev_ms: ingest.request_latency.as_ref().map(|l| l.stddev_ms),
+                p50_ms: ingest.request_latency.as_ref().map(|l| l.p50_ms),
+                p90_ms: ingest.request_latency.as_ref().map(|l| l.p90_ms),
+                p99_ms: ingest.request_latency.as_ref().map(|l| l.p99_ms),
+                p999_ms: ingest.request_latency.as_ref().map(|l| l.p999_ms),
+                max_ms: ingest.request_latency.as_ref().map(|l| l.max_ms),
+                ..CsvRow::default()
+            })?;
+        }
+        for index in &self.indexes {
+            writer.serialize(CsvRow {
+                kind: "index",
+                backend: Some(index.backend),
+                name: &index.name,
+                duration_ms: index.build_ms,
+                size_bytes: Some(index.size_bytes),
+                ..CsvRow::default()
+            })?;
+        }
+        for storage in &self.storage {
+            writer.serialize(CsvRow {
+                kind: "storage",
+                backend: Some(storage.backend),
+                name: &storage.name,
+                documents: Some(storage.documents as usize),
+                size_bytes: Some(storage.total_bytes),
+                ..CsvRow::default()
+            })?;
+        }
+        for plan in &self.plans {
+            writer.serialize(CsvRow {
+                kind: "plan",
+                backend: Some(plan.backend),
+                name: &plan.name,
+                index_profile: plan.index_profile.as_deref(),
+                duration_ms: Some(plan.execution_ms),
+                shared_hit_blocks: Some(plan.shared_hit_blocks),
+                shared_read_blocks: Some(plan.shared_read_blocks),
+                indexes_used: Some(plan.indexes_used.join(";")),
+                ..CsvRow::default()
+            })?;
+        }
+        for verification in &self.verification {
+            for (backend, count, missing) in [
+                (Backend::Postgres, verification.postgres_count, verification.only_postgres),
+                (Backend::Elasticsearch, verification.elasticsearch_count, verification.only_elasticsearch),
+            ] {
+                writer.serialize(CsvRow {
+                    kind: "verify",
+                    backend: Some(backend),
+                    name: &verification.name,
+                    result_count: Some(count),
+                    errors: Some(missing),
+                    ..CsvRow::default()
+                })?;
+            }
+        }
+        for query in &self.queries {
+            writer.serialize(CsvRow {
+                kind: "query",
+                backend: Some(query.backend),
+                name: &query.name,
+                mode: Some(&query.mode),
+                index_profile: query.index_profile.as_deref(),
+                duration_ms: None,
+                documents: None,
+                docs_per_sec: None,
+                size_bytes: None,
+                shared_hit_blocks: None,
+                shared_read_blocks: None,
+                indexes_used: None,
+                result_count: Some(query.result_count),
+                errors: Some(query.errors),
+                retries: None,
+                samples: Some(query.latency.samples),
+                throughput_qps: Some(query.throughput_qps),
+                min_ms: Some(query.latency.min_ms),
+                mean_ms: Some(query.latency.mean_ms),
+                stddev_ms: Some(query.latency.stddev_ms),
+                p50_ms: Some(query.latency.p50_ms),
+                p90_ms: Some(query.latency.p90_ms),
+                p99_ms: Some(query.latency.p99_ms),
+                p999_ms: Some(query.latency.p999_ms),
+                max_ms: Some(query.latency.max_ms),
+            })?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+// src/stats.rs
+use std::time::Duration;
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+
+// Track latencies between 1µs and 1h with 3 significant digits of precision
+const HISTOGRAM_LOW_US: u64 = 1;
+const HISTOGRAM_HIGH_US: u64 = 3_600_000_000;
+const HISTOGRAM_SIGFIG: u8 = 3;
+
+// Summary of the latencies recorded for one query, in milliseconds
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LatencyStats {
+    pub samples: u64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+    pub mean_ms: f64,
+    pub stddev_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub p999_ms: f64,
+}
+
+// HDR histogram of request latencies, recorded in microseconds
+pub struct LatencyRecorder {
+    histogram: Histogram<u64>,
+}
+
+impl LatencyRecorder {
+    pub fn new() -> Self {
+        let histogram = Histogram::new_with_bounds(HISTOGRAM_LOW_US, HISTOGRAM_HIGH_US, HISTOGRAM_SIGFIG)
+            .expect("valid histogram bounds");
+        Self { histogram }
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        // Values outside the bounds are clamped rather than dropped
+        self.histogram.saturating_record(latency.as_micros() as u64);
+    }
+
+    // Fold another recorder (e.g. from a concurrent worker) into this one
+    pub fn merge(&mut self, other: &LatencyRecorder) {
+        self.histogram.add(&other.histogram).expect("histograms share the same bounds");
+    }
+
+    pub fn stats(&self) -> LatencyStats {
+        if self.histogram.is_empty() {
+            return LatencyStats {
+                samples: 0,
+                min_ms: 0.0,
+                max_ms: 0.0,
+                mean_ms: 0.0,
+                stddev_ms: 0.0,
+                p50_ms: 0.0,
+                p90_ms: 0.0,
+                p99_ms: 0.0,
+                p999_ms: 0.0,
+            };
+        }
+        let us_to_ms = |us: u64| us as f64 / 1000.0;
+        LatencyStats {
+            samples: self.histogram.len(),
+            min_ms: us_to_ms(self.histogram.min()),
+            max_ms: us_to_ms(self.histogram.max()),
+            mean_ms: self.histogram.mean() / 1000.0,
+            stddev_ms: self.histogram.stdev() / 1000.0,
+            p50_ms: us_to_ms(self.histogram.value_at_quantile(0.50)),
+            p90_ms: us_to_ms(self.histogram.value_at_quantile(0.90)),
+            p99_ms: us_to_ms(self.histogram.value_at_quantile(0.99)),
+            p999_ms: us_to_ms(self.histogram.value_at_quantile(0.999)),
+        }
+    }
+}
+
+// --- Table Output ---
+
+pub fn print_stats_header() {
+    println!(
+        "{:<30} | {:<8} | {:>6} | {:>9} | {:>9} | {:>9} | {:>9} | {:>9} | {:>9} | {:>9} | {:>9}",
+        "Query Type", "Count", "Runs", "min", "mean", "p50", "p90", "p99", "p99.9", "max", "stddev"
+    );
+    println!("{:-<150}", "");
+}
+
+pub fn print_stats_row(query_desc: &str, count: usize, stats: &LatencyStats) {
+    println!(
+        "{:<30} | {:<8} | {:>6} | {:>9.4} | {:>9.4} | {:>9.4} | {:>9.4} | {:>9.4} | {:>9.4} | {:>9.4} | {:>9.4}",
+        query_desc,
+        count,
+        stats.samples,
+        stats.min_ms,
+        stats.mean_ms,
+        stats.p50_ms,
+        stats.p90_ms,
+        stats.p99_ms,
+        stats.p999_ms,
+        stats.max_ms,
+        stats.stddev_ms
+    );
+}
@@ -0,0 +1,91 @@
+// src/stream.rs
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::BenchmarkError;
+use crate::generate_data::{DocumentGenerator, GenerationSettings};
+use crate::logging;
+
+// Documents flowing from a producer thread to one loader. The channel is bounded, so a slow
+// loader blocks the producer instead of letting documents pile up in memory.
+pub type DocumentStream = mpsc::Receiver<Result<Value, BenchmarkError>>;
+
+// Where the documents of a load come from
+#[derive(Debug, Clone)]
+pub enum DocumentSource {
+    Generated(GenerationSettings),
+    File(PathBuf),
+}
+
+impl DocumentSource {
+    // Known up front for generated data only; used to size progress bars
+    pub fn expected_len(&self) -> Option<u64> {
+        match self {
+            DocumentSource::Generated(settings) => Some(settings.count as u64),
+            DocumentSource::File(_) => None,
+        }
+    }
+
+    // Start a producer for a fresh pass over the documents. Every call yields the same documents
+    // (same seed, same file), so each backend gets its own stream of an identical corpus.
+    pub fn stream(&self, capacity: usize) -> DocumentStream {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let source = self.clone();
+        tokio::task::spawn_blocking(move || match source {
+            DocumentSource::Generated(settings) => {
+                for doc in DocumentGenerator::new(&settings) {
+                    // A closed channel means the loader gave up; stop producing
+                    if tx.blocking_send(Ok(doc)).is_err() {
+                        return;
+                    }
+                }
+            }
+            DocumentSource::File(path) => {
+                if let Err(e) = read_ndjson(&path, &tx) {
+                    let _ = tx.blocking_send(Err(e));
+                }
+            }
+        });
+        rx
+    }
+}
+
+fn read_ndjson(path: &Path, tx: &mpsc::Sender<Result<Value, BenchmarkError>>) -> Result<(), BenchmarkError> {
+    let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if tx.blocking_send(Ok(serde_json::from_str(&line)?)).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Progress bar for a stream whose length may be unknown
+pub fn progress_bar(expected_len: Option<u64>) -> indicatif::ProgressBar {
+    if !logging::progress_bars_enabled() {
+        return indicatif::ProgressBar::hidden();
+    }
+    match expected_len {
+        Some(len) => {
+            let pb = indicatif::ProgressBar::new(len);
+            pb.set_style(indicatif::ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}")
+                .unwrap()
+                .progress_chars("#>-"));
+            pb
+        }
+        None => {
+            let pb = indicatif::ProgressBar::new_spinner();
+            pb.set_style(indicatif::ProgressStyle::default_spinner()
+                .template("{spinner:.green} [{elapsed_precise}] {pos} documents ({per_sec}) {msg}")
+                .unwrap());
+            pb
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// src/verify.rs
+use std::hash::{DefaultHasher, Hash, Hasher};
+use elasticsearch::Elasticsearch;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde_json::Value;
+use tokio_postgres::Client;
+use tracing::{info, warn};
+
+use crate::BenchmarkError;
+use crate::elastic;
+use crate::postgres;
+use crate::report::QueryVerification;
+use crate::workload::Workload;
+
+// Mismatching documents listed per query and backend
+const MISMATCH_SAMPLES: usize = 3;
+
+// Runs both variants of every paired query without a limit, `draws` times with fresh parameters, and
+// compares the hit counts and the keys of the matched documents
+pub async fn verify_workload(pg: &Client, table: &str, es: &Elasticsearch, index: &str, workload: &Workload, draws: usize) -> Result<Vec<QueryVerification>, BenchmarkError> {
+    println!("{:<30} | {:>10} | {:>10} | {:>8} | {:>8} | {:<8} | Params", "Query Type", "PG hits", "ES hits", "only PG", "only ES", "Result");
+    println!("{:-<120}", "");
+
+    let mut rng = StdRng::from_os_rng();
+    let mut results = Vec::new();
+    for spec in &workload.queries {
+        let (Some(pg_spec), Some(es_spec)) = (&spec.postgres, &spec.elasticsearch) else {
+            info!("Skipping '{}': not defined for both backends", spec.name);
+            continue;
+        };
+        for _ in 0..draws {
+            // One draw for both variants so they answer the same question
+            let values = spec.sample_params(&mut rng);
+            let params = spec.pg_params(pg_spec, &values, &mut rng)?;
+            let (pg_count, pg_keys) = postgres::matching_keys(pg, table, pg_spec, &params).await?;
+            let (es_total, es_keys) = elastic::matching_keys(es, index, &spec.es_query(es_spec, &values)).await?;
+
+            let only_pg: Vec<&String> = pg_keys.difference(&es_keys).collect();
+            let only_es: Vec<&String> = es_keys.difference(&pg_keys).collect();
+
+            let result = QueryVerification {
+                name: spec.name.clone(),
+                params: values,
+                postgres_count: pg_count,
+                elasticsearch_count: es_total as usize,
+                only_postgres: only_pg.len(),
+                only_elasticsearch: only_es.len(),
+            };
+            println!(
+                "{:<30} | {:>10} | {:>10} | {:>8} | {:>8} | {:<8} | {}",
+                result.name, result.postgres_count, result.elasticsearch_count, result.only_postgres, result.only_elasticsearch,
+                if result.matches() { "ok" } else { "MISMATCH" }, serde_json::to_string(&result.params)?
+            );
+            // Titles are only looked up for the few documents that get logged
+            for key in only_pg.iter().take(MISMATCH_SAMPLES) {
+                let title = postgres::document_title(pg, table, key).await?;
+                warn!("'{}': only PostgreSQL matched {}", spec.name, describe(key, title));
+            }
+            for key in only_es.iter().take(MISMATCH_SAMPLES) {
+                let title = elastic::document_title(es, index, key).await?;
+                warn!("'{}': only Elasticsearch matched {}", spec.name, describe(key, title));
+            }
+            results.push(result);
+        }
+    }
+    println!("{:-<120}", "");
+
+    let mismatches = results.iter().filter(|r| !r.matches()).count();
+    println!("Verification: {} of {} query draws match on both backends", results.len() - mismatches, results.len());
+    Ok(results)
+}
+
+// Key of a document without an `id` (loaded from files or by older versions). Both sides hash the
+// parsed JSON, whose object keys are sorted, so the JSONB normalisation of PostgreSQL does not matter.
+pub fn fingerprint(doc: &Value) -> String {
+    let mut hasher = DefaultHasher::new();
+    doc.to_string().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn describe(key: &str, title: Option<String>) -> String {
+    match title {
+        Some(title) => format!("\"{}\" ({})", title, key),
+        None => format!("document {}", key),
+    }
+}
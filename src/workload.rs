@@ -0,0 +1,449 @@
+// src/workload.rs
+use std::collections::BTreeMap;
+use std::path::Path;
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio_postgres::types::ToSql;
+
+use crate::BenchmarkError;
+
+// Built-in suite, used when no WORKLOAD_FILE is given
+const DEFAULT_WORKLOAD: &str = include_str!("../workloads/default.toml");
+
+fn default_limit() -> usize {
+    10
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+// A named set of queries, each defined for PostgreSQL and/or Elasticsearch
+#[derive(Deserialize, Debug, Clone)]
+pub struct Workload {
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    pub queries: Vec<QuerySpec>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct QuerySpec {
+    pub name: String,
+    // Relative frequency in concurrent mode
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    // Parameters shared by both variants: `$n` references in PostgreSQL, `{{name}}` in Elasticsearch
+    #[serde(default)]
+    pub params: BTreeMap<String, ParamSpec>,
+    pub postgres: Option<PgQuerySpec>,
+    pub elasticsearch: Option<EsQuerySpec>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PgQuerySpec {
+    // WHERE predicate over the `data` column, using $1..$n for params
+    #[serde(rename = "where")]
+    pub predicate: String,
+    #[serde(default)]
+    pub params: Vec<PgParamSpec>,
+}
+
+// A positional PostgreSQL parameter: the name of a query parameter, or a parameter of its own
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum PgParamSpec {
+    Named(String),
+    Inline(ParamSpec),
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamType {
+    Jsonb,
+    Text,
+    Int,
+    Float,
+    Bool,
+}
+
+// Where the values of a parameter come from: a fixed `value`, one of `choices`, or a number
+// drawn uniformly from the inclusive `range`. Sampled values are drawn again for every execution.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ParamSpec {
+    #[serde(rename = "type")]
+    pub kind: ParamType,
+    pub value: Option<Value>,
+    pub choices: Option<Vec<Value>>,
+    pub range: Option<(f64, f64)>,
+}
+
+impl ParamSpec {
+    pub fn sample(&self, rng: &mut impl Rng) -> Value {
+        if let Some(choices) = &self.choices {
+            return choices.choose(rng).cloned().unwrap_or(Value::Null);
+        }
+        match (self.range, self.kind) {
+            (Some((low, high)), ParamType::Int) => Value::from(rng.random_range(low as i64..=high as i64)),
+            (Some((low, high)), _) => Value::from(rng.random_range(low..=high)),
+            (None, _) => self.value.clone().unwrap_or(Value::Null),
+        }
+    }
+
+    fn validate(&self, query: &str) -> Result<(), BenchmarkError> {
+        let invalid = |reason: &str| BenchmarkError::Workload(format!("query '{}': parameter {}", query, reason));
+        let sources = [self.value.is_some(), self.choices.is_some(), self.range.is_some()];
+        if sources.iter().filter(|set| **set).count() != 1 {
+            return Err(invalid("needs exactly one of value, choices or range"));
+        }
+        if let Some((low, high)) = self.range {
+            if !matches!(self.kind, ParamType::Int | ParamType::Float) || low > high {
+                return Err(invalid("range needs an int or float type and low <= high"));
+            }
+        }
+        if self.choices.as_ref().is_some_and(Vec::is_empty) {
+            return Err(invalid("choices must not be empty"));
+        }
+        // Fixed values and choices must fit the declared type
+        for value in self.value.iter().chain(self.choices.iter().flatten()) {
+            PgParam::new(self.kind, value).map_err(|_| invalid(&format!("value {} is not of type {:?}", value, self.kind)))?;
+        }
+        Ok(())
+    }
+}
+
+// Typed query parameter, bound positionally to $1..$n
+#[derive(Debug, Clone)]
+pub enum PgParam {
+    Jsonb(Value),
+    Text(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl PgParam {
+    pub fn new(kind: ParamType, value: &Value) -> Result<Self, BenchmarkError> {
+        let param = match kind {
+            ParamType::Jsonb => Some(PgParam::Jsonb(value.clone())),
+            ParamType::Text => value.as_str().map(|v| PgParam::Text(v.to_string())),
+            ParamType::Int => value.as_i64().map(PgParam::Int),
+            ParamType::Float => value.as_f64().map(PgParam::Float),
+            ParamType::Bool => value.as_bool().map(PgParam::Bool),
+        };
+        param.ok_or_else(|| BenchmarkError::Workload(format!("{} is not a valid {:?} parameter", value, kind)))
+    }
+
+    pub fn as_sql(&self) -> &(dyn ToSql + Sync) {
+        match self {
+            PgParam::Jsonb(v) => v,
+            PgParam::Text(v) => v,
+            PgParam::Int(v) => v,
+            PgParam::Float(v) => v,
+            PgParam::Bool(v) => v,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct EsQuerySpec {
+    // Query clause placed under "query" in the search body; a string "{{name}}" is replaced by the
+    // value of that parameter, and "{{name}}" inside a longer string by its text
+    pub query: Value,
+}
+
+// What a benchmark query fetches: the first `limit` matches, or just the exact number of matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryVariant {
+    Page,
+    Count,
+}
+
+impl QueryVariant {
+    // Count variants only run when asked for, after all page variants
+    fn selected(count_totals: bool) -> &'static [QueryVariant] {
+        if count_totals { &[QueryVariant::Page, QueryVariant::Count] } else { &[QueryVariant::Page] }
+    }
+
+    // Report name of this variant of a query
+    pub fn query_name(self, name: &str) -> String {
+        match self {
+            QueryVariant::Page => name.to_string(),
+            QueryVariant::Count => format!("{} [count]", name),
+        }
+    }
+}
+
+// Values drawn for one execution of a query, by parameter name
+pub type ParamValues = BTreeMap<String, Value>;
+
+impl QuerySpec {
+    // One draw of the named parameters; both variants rendered from it ask the same question
+    pub fn sample_params(&self, rng: &mut impl Rng) -> ParamValues {
+        self.params.iter().map(|(name, spec)| (name.clone(), spec.sample(rng))).collect()
+    }
+
+    // Positional PostgreSQL parameters for a draw; inline parameters are drawn here
+    pub fn pg_params(&self, pg: &PgQuerySpec, values: &ParamValues, rng: &mut impl Rng) -> Result<Vec<PgParam>, BenchmarkError> {
+        pg.params
+            .iter()
+            .map(|param| match param {
+                PgParamSpec::Named(name) => {
+                    let spec = self.params.get(name).ok_or_else(|| {
+                        BenchmarkError::Workload(format!("query '{}': unknown parameter '{}'", self.name, name))
+                    })?;
+                    PgParam::new(spec.kind, values.get(name).unwrap_or(&Value::Null))
+                }
+                PgParamSpec::Inline(spec) => PgParam::new(spec.kind, &spec.sample(rng)),
+            })
+            .collect()
+    }
+
+    pub fn es_query(&self, es: &EsQuerySpec, values: &ParamValues) -> Value {
+        render_template(&es.query, values)
+    }
+
+    fn validate(&self) -> Result<(), BenchmarkError> {
+        if self.postgres.is_none() && self.elasticsearch.is_none() {
+            return Err(BenchmarkError::Workload(format!(
+                "query '{}' defines neither a postgres nor an elasticsearch variant", self.name
+            )));
+        }
+        for spec in self.params.values() {
+            spec.validate(&self.name)?;
+        }
+        for param in self.postgres.iter().flat_map(|pg| &pg.params) {
+            match param {
+                PgParamSpec::Named(name) if !self.params.contains_key(name) => {
+                    return Err(BenchmarkError::Workload(format!("query '{}': unknown parameter '{}'", self.name, name)));
+                }
+                PgParamSpec::Named(_) => {}
+                PgParamSpec::Inline(spec) => spec.validate(&self.name)?,
+            }
+        }
+        if let Some(es) = &self.elasticsearch {
+            let mut names = Vec::new();
+            template_placeholders(&es.query, &mut names);
+            if let Some(name) = names.iter().find(|name| !self.params.contains_key(*name)) {
+                return Err(BenchmarkError::Workload(format!("query '{}': unknown parameter '{{{{{}}}}}'", self.name, name)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn render_template(template: &Value, values: &ParamValues) -> Value {
+    match template {
+        Value::String(text) => {
+            // A whole-string placeholder keeps the type of the value, so numbers stay numbers
+            if let Some(value) = placeholder(text).and_then(|name| values.get(name)) {
+                return value.clone();
+            }
+            let mut rendered = text.clone();
+            for (name, value) in values {
+                let value_text = value.as_str().map_or_else(|| value.to_string(), str::to_string);
+                rendered = rendered.replace(&format!("{{{{{}}}}}", name), &value_text);
+            }
+            Value::String(rendered)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|item| render_template(item, values)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields.iter().map(|(key, value)| (key.clone(), render_template(value, values))).collect()
+        ),
+        other => other.clone(),
+    }
+}
+
+fn placeholder(text: &str) -> Option<&str> {
+    text.strip_prefix("{{").and_then(|rest| rest.strip_suffix("}}")).map(str::trim)
+}
+
+fn template_placeholders(template: &Value, names: &mut Vec<String>) {
+    match template {
+        Value::String(text) => {
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("{{") {
+                let Some(end) = rest[start..].find("}}") else { break };
+                names.push(rest[start + 2..start + end].trim().to_string());
+                rest = &rest[start + end + 2..];
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| template_placeholders(item, names)),
+        Value::Object(fields) => fields.values().for_each(|value| template_placeholders(value, names)),
+        _ => {}
+    }
+}
+
+impl Workload {
+    // Load from WORKLOAD_FILE-style path, picking the format from the file extension
+    pub fn from_file(path: &Path) -> Result<Self, BenchmarkError> {
+        let text = std::fs::read_to_string(path)?;
+        let workload: Workload = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&text)
+                .map_err(|e| BenchmarkError::Workload(format!("{}: {}", path.display(), e)))?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&text)
+                .map_err(|e| BenchmarkError::Workload(format!("{}: {}", path.display(), e)))?,
+            Some("json") => serde_json::from_str(&text)
+                .map_err(|e| BenchmarkError::Workload(format!("{}: {}", path.display(), e)))?,
+            _ => return Err(BenchmarkError::Workload(format!(
+                "{}: unsupported workload format (expected .toml, .yaml, .yml or .json)", path.display()
+            ))),
+        };
+        workload.validate()?;
+        Ok(workload)
+    }
+
+    pub fn builtin() -> Result<Self, BenchmarkError> {
+        let workload: Workload = toml::from_str(DEFAULT_WORKLOAD)
+            .map_err(|e| BenchmarkError::Workload(format!("built-in workload: {}", e)))?;
+        workload.validate()?;
+        Ok(workload)
+    }
+
+    fn validate(&self) -> Result<(), BenchmarkError> {
+        if self.queries.is_empty() {
+            return Err(BenchmarkError::Workload("workload defines no queries".to_string()));
+        }
+        for query in &self.queries {
+            query.validate()?;
+        }
+        Ok(())
+    }
+
+    pub fn pg_queries(&self) -> impl Iterator<Item = (&QuerySpec, &PgQuerySpec)> {
+        self.queries.iter().filter_map(|q| q.postgres.as_ref().map(|pg| (q, pg)))
+    }
+
+    pub fn es_queries(&self) -> impl Iterator<Item = (&QuerySpec, &EsQuerySpec)> {
+        self.queries.iter().filter_map(|q| q.elasticsearch.as_ref().map(|es| (q, es)))
+    }
+
+    pub fn pg_variants(&self, count_totals: bool) -> impl Iterator<Item = (QueryVariant, &QuerySpec, &PgQuerySpec)> {
+        QueryVariant::selected(count_totals).iter().flat_map(move |variant| self.pg_queries().map(move |(q, pg)| (*variant, q, pg)))
+    }
+
+    pub fn es_variants(&self, count_totals: bool) -> impl Iterator<Item = (QueryVariant, &QuerySpec, &EsQuerySpec)> {
+        QueryVariant::selected(count_totals).iter().flat_map(move |variant| self.es_queries().map(move |(q, es)| (*variant, q, es)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use serde_json::json;
+
+    fn values(pairs: &[(&str, Value)]) -> ParamValues {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.clone())).collect()
+    }
+
+    fn workload(toml: &str) -> Result<Workload, BenchmarkError> {
+        let workload: Workload = toml::from_str(toml).expect("test workload parses");
+        workload.validate()?;
+        Ok(workload)
+    }
+
+    fn param(toml: &str) -> ParamSpec {
+        toml::from_str(toml).expect("test parameter parses")
+    }
+
+    #[test]
+    fn whole_string_placeholder_keeps_the_value_type() {
+        let template = json!({ "range": { "att0": { "gt": "{{att0}}" } }, "term": { "flag": "{{ flag }}" } });
+        let rendered = render_template(&template, &values(&[("att0", json!(42)), ("flag", json!(true))]));
+        assert_eq!(rendered, json!({ "range": { "att0": { "gt": 42 } }, "term": { "flag": true } }));
+    }
+
+    #[test]
+    fn placeholders_inside_longer_strings_are_substituted_as_text() {
+        let template = json!({ "match": { "title": { "query": "{{place}} {{count}}", "operator": "and" } }, "tags": ["{{place}}-x"] });
+        let rendered = render_template(&template, &values(&[("place", json!("haven")), ("count", json!(3))]));
+        assert_eq!(rendered, json!({ "match": { "title": { "query": "haven 3", "operator": "and" } }, "tags": ["haven-x"] }));
+    }
+
+    #[test]
+    fn template_placeholders_are_collected_from_nested_values() {
+        let mut names = Vec::new();
+        template_placeholders(&json!({ "a": ["{{one}} and {{ two }}", { "b": "{{three}}" }], "c": 1, "d": "{{unclosed" }), &mut names);
+        assert_eq!(names, ["one", "two", "three"]);
+    }
+
+    #[test]
+    fn unknown_placeholders_are_rejected() {
+        let result = workload(r#"
+            [[queries]]
+            name = "typo"
+            [queries.params]
+            tld = { type = "text", choices = ["com"] }
+            [queries.elasticsearch]
+            query = { term = { "attributes.att2.nested_key" = "{{tdl}}" } }
+        "#);
+        assert!(matches!(result, Err(BenchmarkError::Workload(message)) if message.contains("{{tdl}}")));
+    }
+
+    #[test]
+    fn unknown_postgres_parameters_are_rejected() {
+        let result = workload(r#"
+            [[queries]]
+            name = "typo"
+            [queries.postgres]
+            where = "data -> 'tags' ? $1"
+            params = ["tag"]
+        "#);
+        assert!(matches!(result, Err(BenchmarkError::Workload(message)) if message.contains("unknown parameter 'tag'")));
+    }
+
+    #[test]
+    fn builtin_workload_is_valid() {
+        assert!(!Workload::builtin().unwrap().queries.is_empty());
+    }
+
+    #[test]
+    fn parameters_need_exactly_one_value_source() {
+        assert!(param(r#"type = "int""#).validate("q").is_err());
+        assert!(param(r#"type = "int"
+            value = 1
+            range = [0, 9]"#).validate("q").is_err());
+        assert!(param(r#"type = "int"
+            value = 1"#).validate("q").is_ok());
+    }
+
+    #[test]
+    fn ranges_need_a_numeric_type_and_ordered_bounds() {
+        assert!(param(r#"type = "int"
+            range = [0, 999]"#).validate("q").is_ok());
+        assert!(param(r#"type = "float"
+            range = [0.5, 0.5]"#).validate("q").is_ok());
+        assert!(param(r#"type = "int"
+            range = [9, 0]"#).validate("q").is_err());
+        assert!(param(r#"type = "text"
+            range = [0, 9]"#).validate("q").is_err());
+    }
+
+    #[test]
+    fn choices_must_be_non_empty_and_of_the_declared_type() {
+        assert!(param(r#"type = "text"
+            choices = ["a", "b"]"#).validate("q").is_ok());
+        assert!(param(r#"type = "text"
+            choices = []"#).validate("q").is_err());
+        assert!(param(r#"type = "text"
+            choices = ["a", 1]"#).validate("q").is_err());
+        assert!(param(r#"type = "int"
+            value = "1""#).validate("q").is_err());
+    }
+
+    #[test]
+    fn sampled_values_stay_within_range_and_choices() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let range = param(r#"type = "int"
+            range = [3, 5]"#);
+        let choices = param(r#"type = "text"
+            choices = ["a", "b"]"#);
+        for _ in 0..100 {
+            assert!((3..=5).contains(&range.sample(&mut rng).as_i64().unwrap()));
+            assert!(["a", "b"].contains(&choices.sample(&mut rng).as_str().unwrap()));
+        }
+    }
+}
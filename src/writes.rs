@@ -0,0 +1,278 @@
+// src/writes.rs
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use elasticsearch::Elasticsearch;
+use rand::rngs::StdRng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use tokio::task::JoinSet;
+use tokio_postgres::Client;
+use tracing::{debug, info, warn};
+
+use crate::BenchmarkError;
+use crate::cli::BenchArgs;
+use crate::elastic;
+use crate::postgres::{self, PgWriteStatements};
+use crate::report::{Backend, QueryReport};
+use crate::stats::{self, LatencyRecorder};
+
+// Documents whose ids background writers of a mixed run pick from
+const MIXED_ID_POOL: usize = 10_000;
+// Pause after a failed background write, doubled per consecutive failure, so a backend that is down
+// is not hammered
+const WRITE_ERROR_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_WRITE_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+// att0 is drawn from 0..ATT0_VALUES, so each delete-by-query value matches about 0.1% of the documents
+const ATT0_VALUES: i64 = 1_000;
+
+// Write operations run against loaded documents; both backends report them under the same names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOp {
+    // jsonb_set on PostgreSQL, a partial document _update on Elasticsearch
+    SetAttribute,
+    // jsonb_set of a computed value on PostgreSQL, a painless script _update on Elasticsearch
+    IncrementAttribute,
+    DeleteById,
+    DeleteByQuery,
+}
+
+impl WriteOp {
+    // Updates first: deletes shrink the data the later operations run on
+    const ALL: [WriteOp; 4] = [WriteOp::SetAttribute, WriteOp::IncrementAttribute, WriteOp::DeleteById, WriteOp::DeleteByQuery];
+    // What background writers of a mixed run do; deletes would shrink the data the reads run on
+    const UPDATES: [WriteOp; 2] = [WriteOp::SetAttribute, WriteOp::IncrementAttribute];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WriteOp::SetAttribute => "update: set attributes.att0",
+            WriteOp::IncrementAttribute => "update: increment att0",
+            WriteOp::DeleteById => "delete: by id",
+            WriteOp::DeleteByQuery => "delete: by query att0 = N",
+        }
+    }
+}
+
+// Target of one write: the document id for by-id operations, plus the value to set, the increment,
+// or the att0 value to delete by
+pub struct WriteInput<'a> {
+    pub id: &'a str,
+    pub value: i64,
+}
+
+// A backend connection dedicated to writes
+pub enum Writer {
+    Postgres { client: Client, statements: PgWriteStatements },
+    Elasticsearch { client: Elasticsearch, index: String },
+}
+
+impl Writer {
+    // Opens its own connection, so writes contend with reads on the server rather than on one client
+    pub async fn postgres(table: &str) -> Result<Self, BenchmarkError> {
+        let client = postgres::connect_postgres().await?;
+        let statements = postgres::prepare_pg_writes(&client, table).await?;
+        Ok(Writer::Postgres { client, statements })
+    }
+
+    pub fn elasticsearch(client: &Elasticsearch, index: &str) -> Self {
+        Writer::Elasticsearch { client: client.clone(), index: index.to_string() }
+    }
+
+    fn backend(&self) -> Backend {
+        match self {
+            Writer::Postgres { .. } => Backend::Postgres,
+            Writer::Elasticsearch { .. } => Backend::Elasticsearch,
+        }
+    }
+
+    async fn sample_ids(&self, count: usize) -> Result<Vec<String>, BenchmarkError> {
+        match self {
+            Writer::Postgres { client, statements } => postgres::sample_doc_ids(client, &statements.table, count).await,
+            Writer::Elasticsearch { client, index } => elastic::sample_doc_ids(client, index, count).await,
+        }
+    }
+
+    // Returns how many documents the write changed
+    async fn run(&self, op: WriteOp, input: &WriteInput<'_>) -> Result<u64, BenchmarkError> {
+        match self {
+            Writer::Postgres { client, statements } => postgres::run_pg_write(client, statements, op, input).await,
+            Writer::Elasticsearch { client, index } => elastic::run_es_write(client, index, op, input).await,
+        }
+    }
+}
+
+// Runs every write operation `warmup + iterations` times, one at a time. Deletes remove documents for
+// good, so reload the data before benchmarking reads again.
+pub async fn benchmark_writes(writer: &Writer, settings: &BenchArgs) -> Result<Vec<QueryReport>, BenchmarkError> {
+    let backend = writer.backend();
+    let runs = (settings.warmup + settings.iterations) as usize;
+    let ids = writer.sample_ids(runs).await?;
+    if ids.is_empty() {
+        warn!("No {} documents with an id to write to; skipping the write benchmarks", backend.label());
+        return Ok(Vec::new());
+    }
+    if ids.len() < runs {
+        warn!("Only {} {} documents with an id; deletes by id will repeat ids", ids.len(), backend.label());
+    }
+    info!("{} warmup + {} measured iterations per write", settings.warmup, settings.iterations);
+    stats::print_stats_header();
+
+    let mut rng = StdRng::from_os_rng();
+    let mut att0_values: Vec<i64> = (0..ATT0_VALUES).collect();
+    att0_values.shuffle(&mut rng);
+
+    let mut reports = Vec::new();
+    for op in WriteOp::ALL {
+        let mut recorder = LatencyRecorder::new();
+        let mut affected = 0;
+        for run in 0..runs {
+            let input = match op {
+                WriteOp::SetAttribute => WriteInput { id: random_id(&ids, &mut rng), value: rng.random_range(0..ATT0_VALUES) },
+                WriteOp::IncrementAttribute => WriteInput { id: random_id(&ids, &mut rng), value: 1 },
+                // Every delete gets its own document so it actually removes something
+                WriteOp::DeleteById => WriteInput { id: &ids[run % ids.len()], value: 0 },
+                WriteOp::DeleteByQuery => WriteInput { id: "", value: att0_values[run % att0_values.len()] },
+            };
+            let start = Instant::now();
+            affected = writer.run(op, &input).await?;
+            if run >= settings.warmup as usize {
+                recorder.record(start.elapsed());
+            }
+        }
+
+        let write_stats = recorder.stats();
+        stats::print_stats_row(op.name(), affected as usize, &write_stats);
+        let mut report = QueryReport::sequential(backend, op.name(), affected as usize, write_stats);
+        report.mode = "writes".to_string();
+        reports.push(report);
+    }
+    println!("{:-<150}", "");
+    Ok(reports)
+}
+
+fn random_id<'a>(ids: &'a [String], rng: &mut impl Rng) -> &'a str {
+    ids.choose(rng).map_or("", String::as_str)
+}
+
+// Latencies of one background writer, per update operation
+struct WriterResult {
+    recorders: Vec<LatencyRecorder>,
+    errors: Vec<usize>,
+}
+
+// Runs `reads` (a concurrent load test) while every writer keeps updating random documents, and
+// reports both the reads and the writes as mixed-mode results
+pub async fn with_background_writes<F>(writers: Vec<Writer>, reads: F) -> Result<Vec<QueryReport>, BenchmarkError>
+where
+    F: Future<Output = Result<Vec<QueryReport>, BenchmarkError>>,
+{
+    let Some(first) = writers.first() else {
+        return reads.await;
+    };
+    let backend = first.backend();
+    let ids = Arc::new(first.sample_ids(MIXED_ID_POOL).await?);
+    if ids.is_empty() {
+        warn!("No {} documents with an id to write to; running reads without writers", backend.label());
+        return reads.await;
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let started = Instant::now();
+    let mut tasks = JoinSet::new();
+    for writer in writers {
+        let (ids, stop) = (Arc::clone(&ids), Arc::clone(&stop));
+        tasks.spawn(async move {
+            let mut rng = StdRng::from_os_rng();
+            let mut result = WriterResult {
+                recorders: WriteOp::UPDATES.iter().map(|_| LatencyRecorder::new()).collect(),
+                errors: vec![0; WriteOp::UPDATES.len()],
+            };
+            let mut consecutive_failures: u32 = 0;
+            while !stop.load(Ordering::Relaxed) {
+                let i = rng.random_range(0..WriteOp::UPDATES.len());
+                let op = WriteOp::UPDATES[i];
+                let value = if op == WriteOp::SetAttribute { rng.random_range(0..ATT0_VALUES) } else { 1 };
+                let input = WriteInput { id: random_id(&ids, &mut rng), value };
+                let start = Instant::now();
+                match writer.run(op, &input).await {
+                    Ok(_) => {
+                        result.recorders[i].record(start.elapsed());
+                        consecutive_failures = 0;
+                    }
+                    Err(e) => {
+                        // Only the first failure of each writer is a warning; the totals are logged at the end
+                        if result.errors.iter().all(|errors| *errors == 0) {
+                            warn!("{} background write '{}' failed: {} (further failures are logged at debug level)", writer.backend().label(), op.name(), e);
+                        } else {
+                            debug!("{} background write '{}' failed: {}", writer.backend().label(), op.name(), e);
+                        }
+                        result.errors[i] += 1;
+                        consecutive_failures += 1;
+                        let backoff = WRITE_ERROR_BACKOFF.saturating_mul(1 << (consecutive_failures - 1).min(16)).min(MAX_WRITE_ERROR_BACKOFF);
+                        tokio::time::sleep(backoff).await;
+                    }
+                }
+            }
+            result
+        });
+    }
+
+    // Stop the writers even when the reads failed
+    let reads = reads.await;
+    stop.store(true, Ordering::Relaxed);
+    let elapsed = started.elapsed();
+    let mut combined = WriterResult {
+        recorders: WriteOp::UPDATES.iter().map(|_| LatencyRecorder::new()).collect(),
+        errors: vec![0; WriteOp::UPDATES.len()],
+    };
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok(result) => {
+                for (recorder, other) in combined.recorders.iter_mut().zip(&result.recorders) {
+                    recorder.merge(other);
+                }
+                for (errors, other) in combined.errors.iter_mut().zip(result.errors) {
+                    *errors += other;
+                }
+            }
+            Err(e) => warn!("Background writer panicked: {}", e),
+        }
+    }
+
+    for ((op, errors), recorder) in WriteOp::UPDATES.iter().zip(&combined.errors).zip(&combined.recorders) {
+        if *errors > 0 {
+            warn!("{} of {} {} background '{}' writes failed", errors, *errors as u64 + recorder.stats().samples, backend.label(), op.name());
+        }
+    }
+
+    let mut reports = reads?;
+    for report in &mut reports {
+        report.mode = "mixed".to_string();
+    }
+    reports.extend(write_results(backend, &combined, elapsed));
+    Ok(reports)
+}
+
+fn write_results(backend: Backend, result: &WriterResult, elapsed: Duration) -> Vec<QueryReport> {
+    println!("\n{} background writes:", backend.label());
+    stats::print_stats_header();
+    let mut reports = Vec::new();
+    for (i, op) in WriteOp::UPDATES.iter().enumerate() {
+        let latency = result.recorders[i].stats();
+        stats::print_stats_row(op.name(), latency.samples as usize, &latency);
+        let throughput_qps = if elapsed.is_zero() { 0.0 } else { latency.samples as f64 / elapsed.as_secs_f64() };
+        reports.push(QueryReport {
+            backend,
+            name: op.name().to_string(),
+            mode: "mixed".to_string(),
+            result_count: latency.samples as usize,
+            errors: result.errors[i],
+            throughput_qps,
+            latency,
+            index_profile: None,
+        });
+    }
+    println!("{:-<150}", "");
+    reports
+}